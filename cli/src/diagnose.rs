@@ -0,0 +1,91 @@
+//! `diagnose` 命令：对目标设备逐项测试常用功能，生成一份能力报告，便于反馈机型相关的问题。
+
+use std::time::Duration;
+
+use anyhow::Result;
+use miai::{Error, Xiaoai};
+
+/// 测试音量调整时使用的音量
+const TEST_VOLUME: u32 = 10;
+
+pub async fn run(xiaoai: &Xiaoai, device_id: &str, yes: bool) -> Result<()> {
+    let mut report = Report::default();
+
+    let hardware = report
+        .record("设备列表", xiaoai.device_info().await)
+        .and_then(|devices| devices.into_iter().find(|d| d.device_id == device_id))
+        .map(|device| {
+            println!("   设备: {} ({})", device.name, device.hardware);
+            device.hardware
+        });
+
+    let status = report.record("读取播放状态", xiaoai.player_status_parsed(device_id).await);
+    let volume = status.as_ref().and_then(|status| status.volume());
+    if let Some(status) = &status {
+        let state = status
+            .play_state()
+            .ok_or_else(|| Error::StatusUnavailable(status.raw.clone()));
+        if let Some(state) = report.record("解析播放状态", state) {
+            println!("   状态: {:?}", state);
+        }
+    }
+
+    match &hardware {
+        Some(hardware) => {
+            report.record(
+                "读取对话记录",
+                xiaoai.get_conversations(device_id, hardware, Some(1)).await,
+            );
+        }
+        None => report.skip("读取对话记录", "未知机型"),
+    }
+
+    if !yes {
+        report.skip("调整音量", "需要 --yes");
+        report.skip("播报文本", "需要 --yes");
+    } else if let Some(volume) = volume {
+        report.record("调整音量", xiaoai.set_volume(device_id, TEST_VOLUME).await);
+        report.record("播报文本", xiaoai.tts(device_id, "诊断测试").await);
+        // 等待播报结束后再恢复音量
+        tokio::time::sleep(Duration::from_secs(3)).await;
+        report.record("恢复音量", xiaoai.set_volume(device_id, volume).await);
+    } else {
+        report.skip("调整音量", "无法读取当前音量，不能保证恢复");
+        report.record("播报文本", xiaoai.tts(device_id, "诊断测试").await);
+    }
+
+    println!();
+    println!("通过 {} 项，失败 {} 项，跳过 {} 项", report.passed, report.failed, report.skipped);
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Report {
+    passed: usize,
+    failed: usize,
+    skipped: usize,
+}
+
+impl Report {
+    /// 记录一项测试的结果，成功时返回结果以便后续测试使用。
+    fn record<T>(&mut self, name: &str, result: miai::Result<T>) -> Option<T> {
+        match result {
+            Ok(value) => {
+                println!("✅ {}", name);
+                self.passed += 1;
+                Some(value)
+            }
+            Err(e) => {
+                println!("❌ {}: {}", name, e);
+                self.failed += 1;
+                None
+            }
+        }
+    }
+
+    fn skip(&mut self, name: &str, reason: &str) {
+        println!("⏭️  {}: 跳过（{}）", name, reason);
+        self.skipped += 1;
+    }
+}
@@ -0,0 +1,183 @@
+//! `play --file` 使用的临时 HTTP 文件服务。
+//!
+//! 只服务一个文件，支持 `HEAD` 和单段 `Range` 请求，足以满足音箱拉流的需要。
+
+use std::{
+    io::SeekFrom,
+    net::{IpAddr, SocketAddr, UdpSocket},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{Context, Result, ensure};
+use miai::{PlayState, Xiaoai};
+use tokio::{
+    fs::File,
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream},
+};
+use url::Url;
+
+/// 查询播放状态的间隔
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// 在本机局域网地址上临时提供 `path`，并让设备播放它。
+///
+/// 会一直运行到播放结束或按下 Ctrl+C，退出时关闭文件服务。
+pub async fn play_file(xiaoai: &Xiaoai, device_id: &str, path: &Path) -> Result<()> {
+    let metadata = tokio::fs::metadata(path)
+        .await
+        .with_context(|| format!("无法读取文件 {}", path.display()))?;
+    ensure!(metadata.is_file(), "{} 不是文件", path.display());
+
+    let ip = local_ip().context("无法确定本机的局域网地址")?;
+    let listener = TcpListener::bind(SocketAddr::new(ip, 0)).await?;
+    let addr = listener.local_addr()?;
+
+    let file_name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("audio");
+    let mut url = Url::parse(&format!("http://{addr}/"))?;
+    url.path_segments_mut()
+        .expect("http URL 一定有路径")
+        .push(file_name);
+
+    let path = Arc::new(path.to_path_buf());
+    let server = tokio::spawn(serve(listener, path));
+    eprintln!("📂 正在提供文件: {}", url);
+
+    let response = xiaoai.play_url(device_id, url.as_str()).await?;
+    println!("code: {}", response.code);
+    println!("message: {}", response.message);
+    eprintln!("按 Ctrl+C 停止\n");
+
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = wait_until_stopped(xiaoai, device_id) => eprintln!("播放结束"),
+    }
+    server.abort();
+
+    Ok(())
+}
+
+/// 等待设备不再处于播放或暂停状态。
+async fn wait_until_stopped(xiaoai: &Xiaoai, device_id: &str) {
+    loop {
+        // 先等待一会儿，让设备有时间开始拉流
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        match xiaoai.player_status_parsed(device_id).await {
+            Ok(status) => match status.play_state() {
+                Some(PlayState::Play | PlayState::Pause) => continue,
+                _ => return,
+            },
+            Err(e) => eprintln!("获取播放状态失败: {}", e),
+        }
+    }
+}
+
+async fn serve(listener: TcpListener, path: Arc<PathBuf>) {
+    loop {
+        let (stream, peer_addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("接受连接失败: {}", e);
+                continue;
+            }
+        };
+        let path = Arc::clone(&path);
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &path).await {
+                eprintln!("处理连接 {} 时出错: {}", peer_addr, e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(stream: TcpStream, path: &Path) -> Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+
+    // 只关心请求方法和 Range 头
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let is_head = request_line.starts_with("HEAD ");
+    let mut range = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).await? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("range") {
+                range = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    let mut file = File::open(path).await?;
+    let len = file.metadata().await?.len();
+    let range = range.and_then(|range| parse_range(&range, len));
+
+    let (status, start, body_len) = match range {
+        Some((start, end)) => ("206 Partial Content", start, end - start + 1),
+        None => ("200 OK", 0, len),
+    };
+    let mut header = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: {}\r\nContent-Length: {body_len}\r\nAccept-Ranges: bytes\r\nConnection: close\r\n",
+        content_type(path)
+    );
+    if let Some((start, end)) = range {
+        header.push_str(&format!("Content-Range: bytes {start}-{end}/{len}\r\n"));
+    }
+    header.push_str("\r\n");
+    writer.write_all(header.as_bytes()).await?;
+
+    if !is_head {
+        file.seek(SeekFrom::Start(start)).await?;
+        tokio::io::copy(&mut file.take(body_len), &mut writer).await?;
+    }
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
+/// 解析形如 `bytes=start-` 或 `bytes=start-end` 的 Range，返回闭区间。
+fn parse_range(value: &str, len: u64) -> Option<(u64, u64)> {
+    let (start, end) = value.strip_prefix("bytes=")?.split_once('-')?;
+    let last = len.checked_sub(1)?;
+    let start: u64 = start.trim().parse().ok()?;
+    let end = match end.trim() {
+        "" => last,
+        end => end.parse::<u64>().ok()?.min(last),
+    };
+
+    (start <= end).then_some((start, end))
+}
+
+fn content_type(path: &Path) -> &'static str {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("mp3") => "audio/mpeg",
+        Some("m4a" | "mp4") => "audio/mp4",
+        Some("aac") => "audio/aac",
+        Some("flac") => "audio/flac",
+        Some("wav") => "audio/wav",
+        Some("ogg") => "audio/ogg",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 获取本机可被局域网访问的地址。
+fn local_ip() -> std::io::Result<IpAddr> {
+    // 连接 UDP 套接字不会真正发出数据包，只是借助路由表选出本机地址
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    socket.connect("223.5.5.5:80")?;
+
+    Ok(socket.local_addr()?.ip())
+}
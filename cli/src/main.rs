@@ -1,17 +1,47 @@
-use std::{borrow::Cow, fmt::Display, fs::File, io::BufReader, path::PathBuf};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    env,
+    fmt::Display,
+    fs::{self, File},
+    io::{self, BufReader, Read},
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{Duration, SystemTime},
+};
 
-use anyhow::{Context, ensure};
-use clap::{Parser, Subcommand};
+use anyhow::{Context, bail, ensure};
+use clap::{
+    ArgMatches, Args, CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum, ValueHint,
+    parser::ValueSource,
+};
+use clap_complete::Shell;
 use inquire::{Confirm, Password, PasswordDisplayMode, Select, Text};
-use miai::{DeviceInfo, PlayState, Xiaoai, ConversationWatcher};
+use futures_util::future::join_all;
+use miai::{
+    DeviceInfo, LoopMode, NlpResult, PlayState, TtsOptions, Xiaoai, XiaoaiResponse, ConversationWatcher,
+    alarm::{AlarmRepeat, AlarmSpec},
+    login::{Login, VerifyMethod},
+    source::Source,
+    time::Time,
+};
 use url::Url;
 use serde::{Deserialize, Serialize};
 
+mod diagnose;
+mod repl;
+mod file_server;
+mod read_aloud;
+mod script;
+mod table;
 mod ws_server;
 use ws_server::WsServer;
 
 const DEFAULT_AUTH_FILE: &str = "xiaoai-auth.json";
 const DEFAULT_CONFIG_FILE: &str = "config.json";
+/// 一条命令中重复获取设备列表时使用缓存
+const DEVICE_CACHE_TTL: Duration = Duration::from_secs(60);
 
 #[derive(Deserialize, Serialize)]
 struct Config {
@@ -23,10 +53,24 @@ struct Config {
     ws_port: u16,
     #[serde(default)]
     check: bool,
+    /// 监听关键词的设备，也是命令行没有指定设备时使用的设备
     #[serde(default)]
     device_id: String,
     #[serde(default)]
     hardware: String,
+    #[serde(default = "default_volume_step")]
+    volume_step: u32,
+    #[serde(default)]
+    serve: ServeConfig,
+    /// 认证文件，相对路径相对于配置文件所在的目录，命令行的 `--auth-file` 优先
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auth_file: Option<PathBuf>,
+    /// 小爱服务的 API 服务器，命令行的 `--api-server` 优先
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    server: Option<String>,
+    /// HTTP 代理服务器，命令行的 `--proxy` 优先
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    proxy: Option<String>,
     #[serde(flatten)]
     watcher_config: serde_json::Value,
 }
@@ -35,82 +79,330 @@ fn default_ws_port() -> u16 {
     8080
 }
 
+fn default_volume_step() -> u32 {
+    5
+}
+
+/// 配置文件中的 `serve` 部分，用于 `wsapi` 命令
+#[derive(Deserialize, Serialize)]
+struct ServeConfig {
+    /// 监听的地址
+    #[serde(default = "default_bind")]
+    bind: IpAddr,
+    /// 监听的端口，未设置时使用 `ws_port`
+    #[serde(default)]
+    port: Option<u16>,
+    /// 认证文件，未设置时使用命令行的 `--auth-file`
+    #[serde(default)]
+    auth_file: Option<PathBuf>,
+    /// 监听关键词的设备 ID，命令行的 `--watch` 优先
+    #[serde(default)]
+    watch_devices: Vec<String>,
+    /// 每个客户端发送队列的容量，队列满时断开该客户端
+    #[serde(default = "default_queue_capacity")]
+    queue_capacity: usize,
+    /// 访问令牌，设置后客户端需要先鉴权才能发送命令和接收广播
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    auth_token: Option<String>,
+    /// PEM 格式的证书链，和 `tls_key` 同时设置时使用 wss://
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tls_cert: Option<PathBuf>,
+    /// PEM 格式的私钥
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tls_key: Option<PathBuf>,
+    /// 登录状态失效时用配置文件中的账号密码自动重新登录，默认关闭
+    #[serde(default)]
+    auto_relogin: bool,
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self {
+            bind: default_bind(),
+            port: None,
+            auth_file: None,
+            watch_devices: Vec::new(),
+            queue_capacity: default_queue_capacity(),
+            auth_token: None,
+            tls_cert: None,
+            tls_key: None,
+            auto_relogin: false,
+        }
+    }
+}
+
+fn default_bind() -> IpAddr {
+    IpAddr::from([0, 0, 0, 0])
+}
+
+fn default_queue_capacity() -> usize {
+    ws_server::DEFAULT_QUEUE_CAPACITY
+}
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let json = cli.output() == OutputFormat::Json;
 
-    if let Commands::Login = cli.command {
-        // 尝试从配置文件读取用户名和密码
-        let (username, password) = if cli.config_file.exists() {
-            let config_file = File::open(&cli.config_file)?;
-            let config: Config = serde_json::from_reader(BufReader::new(config_file))?;
-            
-            if !config.username.is_empty() && !config.password.is_empty() {
-                eprintln!("使用配置文件中的凭据登录...");
-                (config.username, config.password)
-            } else {
-                // 配置文件存在但凭据为空，提示用户输入
-                let username = Text::new("账号:").prompt()?;
-                let password = Password::new("密码:")
-                    .with_display_toggle_enabled()
-                    .with_display_mode(PasswordDisplayMode::Masked)
-                    .without_confirmation()
-                    .with_help_message("CTRL + R 显示/隐藏密码")
-                    .prompt()?;
-                (username, password)
-            }
+    cli.apply_config(&matches);
+    let result = run(cli).await.map_err(|e| {
+        let expired = e
+            .chain()
+            .any(|cause| matches!(cause.downcast_ref(), Some(miai::Error::SessionExpired)));
+        if expired {
+            e.context("登录已失效，请重新运行 xiaoai login")
         } else {
-            // 配置文件不存在，提示用户输入
-            let username = Text::new("账号:").prompt()?;
-            let password = Password::new("密码:")
-                .with_display_toggle_enabled()
-                .with_display_mode(PasswordDisplayMode::Masked)
-                .without_confirmation()
-                .with_help_message("CTRL + R 显示/隐藏密码")
-                .prompt()?;
-            (username, password)
-        };
-        
-        let xiaoai = Xiaoai::login(&username, &password).await?;
+            e
+        }
+    });
 
-        let can_save = if cli.auth_file.exists() {
-            Confirm::new(&format!("{} 已存在，是否覆盖?", cli.auth_file.display())).prompt()?
+    match result {
+        Err(e) if e.is::<AlreadyReported>() => std::process::exit(1),
+        // JSON 模式下错误也输出到 stdout，便于脚本统一解析
+        Err(e) if json => {
+            println!("{}", serde_json::json!({ "error": format!("{:#}", e) }));
+            std::process::exit(1);
+        }
+        result => result,
+    }
+}
+
+/// 失败的结果已经输出过，`main` 只需以退出码 1 退出，不再输出错误。
+#[derive(Debug)]
+struct AlreadyReported;
+
+impl Display for AlreadyReported {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("执行失败")
+    }
+}
+
+impl std::error::Error for AlreadyReported {}
+
+async fn run(cli: Cli) -> anyhow::Result<()> {
+    if let Commands::Completion { shell } = cli.command {
+        let mut command = Cli::command();
+        clap_complete::generate(shell, &mut command, env!("CARGO_BIN_NAME"), &mut std::io::stdout());
+        return Ok(());
+    }
+
+    if let Commands::Config { action: ConfigAction::Path } = cli.command {
+        if cli.output() == OutputFormat::Json {
+            let output = serde_json::json!({
+                "path": cli.config_file,
+                "exists": cli.config_file.exists(),
+            });
+            println!("{}", output);
+        } else if cli.config_file.exists() {
+            println!("{}", cli.config_file.display());
+        } else {
+            println!("{}（不存在）", cli.config_file.display());
+        }
+        return Ok(());
+    }
+
+    if cli.heartbeat.is_some() {
+        // 只开启心跳日志，其余日志仍由 RUST_LOG 控制
+        let filter = tracing_subscriber::EnvFilter::from_default_env()
+            .add_directive(format!("{}=info", miai::HEARTBEAT_TARGET).parse()?);
+        tracing_subscriber::fmt()
+            .with_env_filter(filter)
+            .with_writer(std::io::stderr)
+            .init();
+    }
+
+    if cli.client.insecure {
+        eprintln!("⚠️  警告: 已使用 --insecure 跳过 TLS 证书校验，连接可能被窃听或篡改，仅用于调试！");
+    }
+
+    if let Commands::Login { check, persistent_only, cookie, username, password_stdin } =
+        &cli.command
+    {
+        let (check, persistent_only) = (*check, *persistent_only);
+        ensure!(
+            !(persistent_only && cli.passphrase.is_some()),
+            "--persistent-only 不能与 --passphrase 同时使用"
+        );
+        let xiaoai = if let Some(cookie) = cookie {
+            let xiaoai = cli
+                .client
+                .apply(Xiaoai::from_cookies(cookie, cli.client.api_server.clone())?)?;
+            ensure!(xiaoai.is_logged_in().await?, "Cookie 无效或已过期");
+            xiaoai
+        } else if let Some(username) = username {
+            let password = login_password(*password_stdin)?;
+            login_with_progress(username, &password, &cli.client).await?
         } else {
+            let (username, password) = read_credentials(&cli.config_file)?;
+            login_with_progress(&username, &password, &cli.client).await?
+        };
+
+        if check {
+            let devices = xiaoai.device_info().await.context("登录成功，但获取设备列表失败")?;
+            if cli.output() == OutputFormat::Json {
+                let output = serde_json::json!({
+                    "user_id": xiaoai.user_id(),
+                    "devices": devices.len(),
+                });
+                println!("{}", output);
+                return Ok(());
+            }
+            println!("✅ 登录成功");
+            println!("用户 ID: {}", xiaoai.user_id().as_deref().unwrap_or("未知"));
+            println!("设备数量: {}", devices.len());
+            return Ok(());
+        }
+
+        let can_save = if !cli.auth_file.exists() {
+            true
+        } else if username.is_some() {
+            // 非交互式登录时直接覆盖，便于在脚本中使用
+            eprintln!("覆盖已有的认证文件 {}", cli.auth_file.display());
             true
+        } else {
+            Confirm::new(&format!("{} 已存在，是否覆盖?", cli.auth_file.display())).prompt()?
         };
 
         if can_save {
-            let mut file = File::create(cli.auth_file)?;
-            xiaoai.save(&mut file).map_err(anyhow::Error::from_boxed)?;
+            let mut file = File::create(&cli.auth_file)?;
+            match &cli.passphrase {
+                Some(passphrase) => xiaoai.save_encrypted(&mut file, passphrase)?,
+                None if persistent_only => xiaoai.save_persistent_only(&mut file)?,
+                None => xiaoai.save(&mut file)?,
+            }
         }
         return Ok(());
     }
 
-    // 以下命令需要登录
-    let xiaoai = cli.xiaoai()?;
-    if let Commands::Device = cli.command {
-        let device_info = xiaoai.device_info().await?;
-        for info in device_info {
-            println!("{}", DisplayDeviceInfo(info));
+    if let Commands::Radio { name } = &cli.command {
+        if name == "list" {
+            if cli.output() == OutputFormat::Json {
+                let stations: Vec<_> = miai::radio::STATIONS
+                    .iter()
+                    .map(|station| {
+                        serde_json::json!({ "id": station.id, "name": station.name, "url": station.url })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&stations)?);
+                return Ok(());
+            }
+            for station in miai::radio::STATIONS {
+                println!("{}\t{}\t{}", station.id, station.name, station.url);
+            }
+            return Ok(());
         }
+    }
+
+    if let Commands::Models = cli.command {
+        if cli.output() == OutputFormat::Json {
+            let models: Vec<_> = miai::model::supported_models()
+                .iter()
+                .map(|model| {
+                    serde_json::json!({
+                        "hardware": model.hardware,
+                        "play_method": format!("{:?}", model.play_method),
+                    })
+                })
+                .collect();
+            println!("{}", serde_json::to_string_pretty(&models)?);
+            return Ok(());
+        }
+        let rows: Vec<_> = miai::model::supported_models()
+            .iter()
+            .map(|model| vec![model.hardware.to_string(), format!("{:?}", model.play_method)])
+            .collect();
+        table::print_table(&["机型", "播放方式"], &rows);
         return Ok(());
     }
 
     // Wsapi 命令 - 启动 WebSocket API 服务器
-    if let Commands::Wsapi = cli.command {
+    if let Commands::Wsapi { watch, all } = &cli.command {
         eprintln!("🌐 启动 WebSocket API 服务器...");
         
         // 加载配置
-        let config_file = File::open(&cli.config_file)?;
+        let config_file = File::open(&cli.config_file)
+            .with_context(|| format!("需要可用的配置文件 {}", cli.config_file.display()))?;
         let config: Config = serde_json::from_reader(BufReader::new(config_file))?;
+        let serve = &config.serve;
+
+        // serve 中未设置的项回退到顶层配置或命令行参数，--watch 优先于配置文件
+        let addr = SocketAddr::new(serve.bind, serve.port.unwrap_or(config.ws_port));
+        let auth_file = serve.auth_file.as_ref().unwrap_or(&cli.auth_file);
+        let watch = if watch.is_empty() { &serve.watch_devices } else { watch };
+        ensure!(addr.port() != 0, "端口不能为 0");
+        ensure!(serve.queue_capacity > 0, "serve.queue_capacity 不能为 0");
+        ensure!(
+            serve.tls_cert.is_some() == serve.tls_key.is_some(),
+            "serve.tls_cert 和 serve.tls_key 需要同时设置"
+        );
+        ensure!(
+            !serve.auto_relogin || (!config.username.is_empty() && !config.password.is_empty()),
+            "开启 serve.auto_relogin 时需要在配置文件中填写 username 和 password"
+        );
+
+        eprintln!("⚙️  服务配置:");
+        eprintln!("  监听地址: {}", addr);
+        eprintln!("  认证文件: {}", auth_file.display());
+        eprintln!("  关键词配置: {}", cli.config_file.display());
+        eprintln!("  发送队列容量: {}", serve.queue_capacity);
+        eprintln!("  访问鉴权: {}", if serve.auth_token.is_some() { "已开启" } else { "未开启" });
+        eprintln!("  TLS: {}", if serve.tls_cert.is_some() { "已开启" } else { "未开启" });
+        eprintln!("  自动重新登录: {}", if serve.auto_relogin { "已开启" } else { "未开启" });
+        if serve.auth_token.is_none() && !addr.ip().is_loopback() {
+            eprintln!("⚠️  警告: 未设置 serve.auth_token，局域网内任何人都能控制音箱");
+        }
+        if *all {
+            eprintln!("  监听设备: 全部");
+        } else if !watch.is_empty() {
+            eprintln!("  监听设备: {}", watch.join(", "));
+        }
+
+        let mut xiaoai = load_xiaoai(auth_file, &cli)?;
+        if serve.auto_relogin {
+            let auth_file = auth_file.clone();
+            let passphrase = cli.passphrase.clone();
+            xiaoai = xiaoai
+                .with_relogin_credentials(&config.username, &config.password)
+                .on_relogin(move |xiaoai| {
+                    match save_xiaoai(xiaoai, &auth_file, passphrase.as_deref()) {
+                        Ok(()) => eprintln!("🔑 已重新登录，并保存到 {}", auth_file.display()),
+                        Err(e) => eprintln!("⚠️  已重新登录，但保存认证文件失败: {:#}", e),
+                    }
+                });
+        }
         
         // 创建 WebSocket 服务器
-        let server = WsServer::new(xiaoai.clone(), config.ws_port);
+        let mut server = WsServer::new(xiaoai.clone(), addr, cli.config_file.clone())
+            .with_heartbeat(cli.heartbeat_interval())
+            .with_queue_capacity(serve.queue_capacity)
+            .with_auth_token(serve.auth_token.clone());
+        if let (Some(cert), Some(key)) = (&serve.tls_cert, &serve.tls_key) {
+            server = server.with_tls(cert, key)?;
+        }
         
-        // 如果启用了 check，获取或验证设备信息
-        if config.check {
-            // 如果配置中没有 device_id，自动获取
-            let (device_id, hardware) = if config.device_id.is_empty() || config.hardware.is_empty() {
+        // 指定了要监听的设备，或在配置中启用了 check 时，启动关键词监听
+        if *all || !watch.is_empty() || config.check {
+            let devices = if *all || !watch.is_empty() {
+                let devices = xiaoai.device_info().await.context("获取设备列表失败")?;
+                ensure!(!devices.is_empty(), "无可用设备，需要在小米音箱 APP 中绑定");
+
+                let mut selected = Vec::new();
+                for device in devices {
+                    if *all || watch.contains(&device.device_id) {
+                        selected.push((device.device_id, device.hardware));
+                    }
+                }
+                for device_id in watch {
+                    ensure!(
+                        selected.iter().any(|(id, _)| id == device_id),
+                        "找不到设备 {}",
+                        device_id
+                    );
+                }
+                selected
+            } else if config.device_id.is_empty() || config.hardware.is_empty() {
+                // 如果配置中没有 device_id，自动获取
                 eprintln!("📱 未配置设备信息，正在自动获取...");
                 
                 let devices = xiaoai.device_info().await.context("获取设备列表失败")?;
@@ -119,7 +411,7 @@ async fn main() -> anyhow::Result<()> {
                 if devices.len() == 1 {
                     let device = &devices[0];
                     eprintln!("✅ 自动选择唯一设备: {} ({})", device.name, device.hardware);
-                    (device.device_id.clone(), device.hardware.clone())
+                    vec![(device.device_id.clone(), device.hardware.clone())]
                 } else {
                     eprintln!("📋 找到 {} 个设备:", devices.len());
                     for (i, device) in devices.iter().enumerate() {
@@ -129,18 +421,24 @@ async fn main() -> anyhow::Result<()> {
                     // 使用第一个设备
                     let device = &devices[0];
                     eprintln!("✅ 自动选择第一个设备: {} ({})", device.name, device.hardware);
-                    eprintln!("💡 提示: 可以在 config.json 中设置 device_id 和 hardware 来指定设备");
-                    (device.device_id.clone(), device.hardware.clone())
+                    eprintln!("💡 提示: 可以在 config.json 中设置 device_id 和 hardware 来指定设备，或使用 --all 监听所有设备");
+                    vec![(device.device_id.clone(), device.hardware.clone())]
                 }
             } else {
-                (config.device_id, config.hardware)
+                vec![(config.device_id, config.hardware)]
             };
             
             let server_watch = server.clone();
-            
+            let mut watcher = tokio::spawn(async move { server_watch.run_watcher(devices).await });
+
             tokio::select! {
                 result = server.run_server() => result?,
-                result = server_watch.run_watcher(device_id, hardware) => result?,
+                result = &mut watcher => result??,
+                _ = tokio::signal::ctrl_c() => {
+                    eprintln!("\n正在停止关键词监听...");
+                    server.shutdown();
+                    watcher.await??;
+                }
             }
         } else {
             server.run_server().await?;
@@ -149,30 +447,281 @@ async fn main() -> anyhow::Result<()> {
         return Ok(());
     }
 
+    // 以下命令需要登录
+    let xiaoai = cli.xiaoai()?;
+    if let Commands::Whoami = cli.command {
+        let account = xiaoai.account_info().await?;
+        let expiry = account
+            .token_expiry
+            .map(|expiry| chrono::DateTime::<chrono::Local>::from(SystemTime::from(expiry)));
+        if cli.output() == OutputFormat::Json {
+            let output = serde_json::json!({
+                "user_id": account.user_id,
+                "devices": account.devices,
+                "expires_at": expiry.map(|expiry| expiry.to_rfc3339()),
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+            return Ok(());
+        }
+        println!("用户 ID: {}", account.user_id);
+        println!("设备数量: {}", account.devices.len());
+        for device in &account.devices {
+            println!("  {} ({})", device.name, device.hardware);
+        }
+        if let Some(expiry) = expiry {
+            println!("登录状态过期时间: {}", expiry.format("%Y-%m-%d %H:%M:%S"));
+        }
+        return Ok(());
+    }
+    if let Commands::Session = cli.command {
+        let expiry = xiaoai
+            .token_expiry()
+            .map(|expiry| chrono::DateTime::<chrono::Local>::from(SystemTime::from(expiry)));
+        let remaining = expiry.map(|expiry| expiry - chrono::Local::now());
+        if cli.output() == OutputFormat::Json {
+            let output = serde_json::json!({
+                "expires_at": expiry.map(|expiry| expiry.to_rfc3339()),
+                "remaining_secs": remaining.map(|remaining| remaining.num_seconds()),
+            });
+            println!("{}", output);
+            return Ok(());
+        }
+        match (expiry, remaining) {
+            (Some(expiry), Some(remaining)) if remaining.num_seconds() > 0 => {
+                println!("登录状态过期时间: {}", expiry.format("%Y-%m-%d %H:%M:%S"));
+                println!(
+                    "剩余: {} 天 {} 小时 {} 分钟",
+                    remaining.num_days(),
+                    remaining.num_hours() % 24,
+                    remaining.num_minutes() % 60
+                );
+            }
+            (Some(expiry), _) => {
+                println!("登录状态已于 {} 过期，需要重新登录", expiry.format("%Y-%m-%d %H:%M:%S"));
+            }
+            _ => println!("认证文件中没有记录过期时间（会话 Cookie），无法预估何时失效"),
+        }
+        return Ok(());
+    }
+    if let Commands::Device { master } = cli.command {
+        let device_info = xiaoai.device_info_with(master).await?;
+        match cli.output() {
+            OutputFormat::Text => {
+                for info in device_info {
+                    println!("{}", DisplayDeviceInfo(info));
+                }
+            }
+            OutputFormat::Table => {
+                let rows: Vec<_> = device_info
+                    .into_iter()
+                    .map(|info| {
+                        vec![
+                            info.name,
+                            info.device_id,
+                            info.hardware,
+                            info.presence.unwrap_or_else(|| "未知".to_string()),
+                            info.rom_version.unwrap_or_else(|| "未知".to_string()),
+                        ]
+                    })
+                    .collect();
+                table::print_table(&["名称", "设备 ID", "机型", "在线状态", "固件"], &rows);
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&device_info)?),
+        }
+        return Ok(());
+    }
+
+    if let Commands::Export { no_redact } = cli.command {
+        let raw = xiaoai.raw_device_info().await?;
+        let devices: Vec<DeviceInfo> = raw.clone().extract_data()?;
+
+        // 逐个设备读取播放器状态，读取失败也记录下来，便于定位机型问题
+        let mut statuses = Vec::with_capacity(devices.len());
+        for info in &devices {
+            let status = match xiaoai.player_status_parsed(&info.device_id).await {
+                Ok(status) => status.raw,
+                Err(e) => serde_json::json!({ "error": e.to_string() }),
+            };
+            statuses.push(serde_json::json!({
+                "device_id": info.device_id,
+                "hardware": info.hardware,
+                "status": status,
+            }));
+        }
+
+        let mut export = serde_json::json!({
+            "exported_at": chrono::Local::now().to_rfc3339(),
+            "device_list": raw.data,
+            "player_status": statuses,
+        });
+        if !no_redact {
+            redact(&mut export);
+        }
+        println!("{}", serde_json::to_string_pretty(&export)?);
+        return Ok(());
+    }
+
+    if let Commands::Repl = cli.command {
+        return repl::run(&xiaoai, &cli).await;
+    }
+
+    if let Commands::Run { script, continue_on_error } = &cli.command {
+        return script::run(&xiaoai, &cli, script, *continue_on_error).await;
+    }
+
     // 以下命令需要设备 ID
+    if let Some(devices) = cli.broadcast_targets(&xiaoai).await? {
+        return broadcast(&xiaoai, &cli, devices).await;
+    }
     let device_id = cli.device_id(&xiaoai).await?;
-    let response = match &cli.command {
-        Commands::Say { text } => xiaoai.tts(&device_id, text).await?,
-        Commands::Play { url } => {
-            if let Some(url) = url {
-                xiaoai.play_url(&device_id, url.as_str()).await?
+
+    run_command(&xiaoai, &cli, &cli.command, &device_id).await
+}
+
+/// 对单台设备执行命令并打印结果。
+///
+/// `command` 和 `cli.command` 分开传入，以便 REPL 复用同一份全局参数执行不同的命令。
+async fn run_command(
+    xiaoai: &Xiaoai,
+    cli: &Cli,
+    command: &Commands,
+    device_id: &str,
+) -> anyhow::Result<()> {
+    let response = match command {
+        Commands::Say { text, file, speed, speaker } => {
+            let options = TtsOptions {
+                speed: *speed,
+                speaker: speaker.clone(),
+            };
+            if let Some(long_text) = say_input(text.as_deref(), file.as_deref())? {
+                read_aloud::run(xiaoai, device_id, &long_text, &options).await?;
+                return Ok(());
+            }
+            send_command(xiaoai, cli, command, device_id).await?
+        }
+        Commands::Play { file: Some(file), .. } => {
+            file_server::play_file(xiaoai, device_id, file).await?;
+            return Ok(());
+        }
+        Commands::VolumeGet => {
+            let volume = xiaoai.get_volume(device_id).await?;
+            if cli.output() == OutputFormat::Json {
+                println!("{}", serde_json::json!({ "volume": volume }));
+            } else {
+                println!("{}", volume);
+            }
+            return Ok(());
+        }
+        Commands::Ask { text, wait: true, .. } => {
+            let result = xiaoai.ask(device_id, text).await?;
+            if cli.output() == OutputFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&result)?);
+            } else if let Some(answer) = &result.answer {
+                println!("{}", answer);
+                if let Some(domain) = &result.domain {
+                    eprintln!("意图: {} {}", domain, result.intent.as_deref().unwrap_or_default());
+                }
             } else {
-                xiaoai.set_play_state(&device_id, PlayState::Play).await?
+                println!("{}", serde_json::to_string_pretty(&result.raw)?);
             }
+            return Ok(());
         }
-        Commands::Volume { volume } => xiaoai.set_volume(&device_id, *volume).await?,
-        Commands::Ask { text } => xiaoai.nlp(&device_id, text).await?,
-        Commands::Pause => xiaoai.set_play_state(&device_id, PlayState::Pause).await?,
-        Commands::Stop => xiaoai.set_play_state(&device_id, PlayState::Stop).await?,
-        Commands::Status => {
-            let status = xiaoai.player_status_parsed(&device_id).await?;
-            // status.raw 已经是 serde_json::Value 类型
-            println!("{}", serde_json::to_string_pretty(&status.raw)?);
+        Commands::Mute => {
+            let volume = xiaoai.mute(device_id).await?;
+            if volume > 0 {
+                let mut volumes = read_muted_volumes(cli);
+                volumes.insert(device_id.to_string(), volume);
+                fs::write(cli.mute_file(), serde_json::to_string_pretty(&volumes)?)
+                    .context("保存静音前的音量失败")?;
+            }
+            if cli.output() == OutputFormat::Json {
+                println!("{}", serde_json::json!({ "previous_volume": volume }));
+            } else {
+                println!("已静音，静音前的音量: {}", volume);
+            }
+            return Ok(());
+        }
+        Commands::Unmute { volume } => {
+            let mut volumes = read_muted_volumes(cli);
+            let saved = volumes.remove(device_id);
+            let volume = volume
+                .or(saved)
+                .context("没有记录静音前的音量，需要指定恢复的音量")?;
+            let response = xiaoai.unmute(device_id, volume).await?;
+            if saved.is_some() {
+                fs::write(cli.mute_file(), serde_json::to_string_pretty(&volumes)?)
+                    .context("保存静音前的音量失败")?;
+            }
+            response
+        }
+        Commands::Dnd { action: DndAction::Status } => {
+            let result = xiaoai.get_do_not_disturb(device_id).await?;
+            print_answer(cli, &result)?;
+            return Ok(());
+        }
+        Commands::Source { action: SourceAction::Get } => {
+            let result = xiaoai.get_source(device_id).await?;
+            print_answer(cli, &result)?;
+            return Ok(());
+        }
+        Commands::Sleep { query: true, .. } => {
+            let result = xiaoai.get_sleep_timer(device_id).await?;
+            print_answer(cli, &result)?;
+            return Ok(());
+        }
+        Commands::Sleep { after: Some(after), local: true, .. } => {
+            eprintln!("⏳ 将在 {} 秒后停止播放，按 Ctrl+C 取消", after.as_secs());
+            xiaoai.stop_after(device_id, *after).await?
+        }
+        Commands::Alarm { action: AlarmAction::List } => {
+            let result = xiaoai.list_alarms(device_id).await?;
+            print_answer(cli, &result)?;
+            return Ok(());
+        }
+        Commands::Toggle => {
+            let state = xiaoai
+                .set_play_state_confirmed(device_id, PlayState::Toggle)
+                .await?;
+            if cli.output() == OutputFormat::Json {
+                println!("{}", serde_json::json!({ "state": format!("{:?}", state).to_lowercase() }));
+            } else {
+                println!("{}", play_state_text(&state));
+            }
+            return Ok(());
+        }
+        Commands::Status { raw } => {
+            let status = xiaoai.player_status_parsed(device_id).await?;
+            match cli.output() {
+                OutputFormat::Table if !raw => {
+                    table::print_table(&["字段", "值"], &table::key_value_rows(&status.raw));
+                }
+                OutputFormat::Text if !raw => {
+                    let unknown = || "未知".to_string();
+                    let state = status.status.as_ref().map(play_state_text);
+                    let track = match (&status.title, &status.artist) {
+                        (Some(title), Some(artist)) => Some(format!("{} - {}", title, artist)),
+                        (Some(title), None) => Some(title.clone()),
+                        _ => None,
+                    };
+                    println!("播放状态: {}", state.unwrap_or_else(unknown));
+                    println!("音量: {}", status.volume.map(|v| v.to_string()).unwrap_or_else(unknown));
+                    println!("曲目: {}", track.unwrap_or_else(unknown));
+                    println!("循环模式: {}", status.loop_type.map(|v| v.to_string()).unwrap_or_else(unknown));
+                }
+                _ => {
+                    // status.raw 已经是 serde_json::Value 类型
+                    println!("{}", serde_json::to_string_pretty(&status.raw)?);
+                }
+            }
+            return Ok(());
+        }
+        Commands::Diagnose { yes } => {
+            diagnose::run(xiaoai, device_id, *yes).await?;
             return Ok(());
         }
         Commands::Check => {
             // 获取设备信息
-            let devices = xiaoai.device_info().await?;
+            let devices = xiaoai.device_info_cached(DEVICE_CACHE_TTL).await?;
             let device_info = devices.iter().find(|d| d.device_id == device_id);
             let hardware = device_info
                 .map(|d| d.hardware.as_str())
@@ -186,8 +735,8 @@ async fn main() -> anyhow::Result<()> {
             eprintln!("按 Ctrl+C 停止监听\n");
             
             // 加载关键词配置
-            let mut watcher = ConversationWatcher::from_json_file(&cli.config_file)
-                .with_context(|| format!("加载配置文件 {} 失败", cli.config_file.display()))?;
+            let mut watcher = ConversationWatcher::from_json_file(&cli.config_file)?;
+            watcher.set_heartbeat(cli.heartbeat_interval());
             
             // 输出已启用的关键词到 stderr
             let enabled_keywords: Vec<_> = watcher.get_enabled_keywords().collect();
@@ -203,29 +752,56 @@ async fn main() -> anyhow::Result<()> {
             
             // 克隆 device_id 以便在闭包中使用
             let device_id_clone = device_id.to_string();
+
+            // 收到 Ctrl+C 后等当前回调处理完再退出
+            let (stop, shutdown) = tokio::sync::watch::channel(false);
+            tokio::spawn(async move {
+                if tokio::signal::ctrl_c().await.is_ok() {
+                    eprintln!("正在停止监听...");
+                    let _ = stop.send(true);
+                }
+            });
             
             // 启动监听
-            watcher.watch(&xiaoai, &device_id, hardware, move |keyword_match| {
+            let stats = watcher.watch_until(xiaoai, device_id, hardware, shutdown, move |keyword_match| {
                 let device_id = device_id_clone.clone();
                 async move {
                     // 输出匹配信息为 JSON
-                    let output = serde_json::json!({
+                    let mut output = serde_json::json!({
                         "timestamp": keyword_match.conversation.time,
                         "query": keyword_match.conversation.query,
                         "matched_keyword": keyword_match.matched_keyword,
                         "device_id": device_id,
                     });
+                    // 与 WebSocket API 的 keyword_match 一致，只在正则表达式匹配时输出捕获组
+                    if !keyword_match.captures.is_empty() {
+                        output["captures"] = serde_json::json!(keyword_match.captures);
+                    }
                     
                     println!("{}", serde_json::to_string(&output)?);
                     
                     Ok(())
                 }
             }).await?;
+            eprintln!(
+                "已停止监听，共轮询 {} 次，匹配 {} 次，跳过重复 {} 次",
+                stats.polls, stats.matches, stats.duplicates
+            );
             
             return Ok(());
         }
+        command if is_simple(command) => send_command(xiaoai, cli, command, device_id).await?,
         _ => unreachable!("所有命令都应该被处理"),
     };
+    if cli.output() == OutputFormat::Json {
+        let output = serde_json::json!({
+            "code": response.code,
+            "message": response.message,
+            "data": response.data,
+        });
+        println!("{}", output);
+        return Ok(());
+    }
     println!("code: {}", response.code);
     println!("message: {}", response.message);
     println!("data: {}", response.data);
@@ -240,59 +816,698 @@ struct Cli {
     command: Commands,
 
     /// 指定认证文件
-    #[arg(long, default_value = DEFAULT_AUTH_FILE)]
+    #[arg(long, default_value = DEFAULT_AUTH_FILE, value_hint = ValueHint::FilePath)]
     auth_file: PathBuf,
 
-    /// 指定配置文件
-    #[arg(short, long, default_value = DEFAULT_CONFIG_FILE)]
+    /// 指定配置文件，默认使用当前目录的 config.json，不存在时使用 $XDG_CONFIG_HOME/xiaoai/config.json
+    #[arg(short, long, default_value_os_t = default_config_file(), value_hint = ValueHint::FilePath)]
     config_file: PathBuf,
 
-    /// 指定设备 ID
+    /// 指定设备 ID，用逗号分隔多个 ID 可以同时对多台设备执行
     #[arg(short, long)]
     device_id: Option<String>,
+
+    /// 按名称指定设备，不区分大小写
+    #[arg(long, conflicts_with = "device_id")]
+    device_name: Option<String>,
+
+    /// 同时对所有设备执行
+    #[arg(long, conflicts_with_all = ["device_id", "device_name"])]
+    all_devices: bool,
+
+    /// 关键词监听时每隔若干秒输出一条心跳日志，默认关闭
+    #[arg(long, value_name = "SECS", value_parser = clap::value_parser!(u64).range(1..))]
+    heartbeat: Option<u64>,
+
+    #[command(flatten)]
+    client: ClientArgs,
+
+    /// 加密认证文件的口令，登录时用它加密保存，其他命令用它解密
+    #[arg(long, env = "XIAOAI_PASSPHRASE", hide_env_values = true)]
+    passphrase: Option<String>,
+
+    /// 输出格式，table 只对 device、status 命令生效，其他命令按 text 输出
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
+
+    /// 以 JSON 输出结果和错误，相当于 --output json
+    #[arg(long, conflicts_with = "output")]
+    json: bool,
+}
+
+/// 网络请求相关的参数
+#[derive(Args)]
+struct ClientArgs {
+    /// 跳过 TLS 证书校验，仅用于通过 mitmproxy 等代理调试
+    #[arg(long)]
+    insecure: bool,
+
+    /// 模拟的米家 APP 版本，旧版本被弃用导致登录失败时可以换成较新的版本
+    #[arg(long, value_name = "VERSION")]
+    app_version: Option<String>,
+
+    /// 登录服务器，用于其他区域的账号（默认 https://account.xiaomi.com/pass/）
+    #[arg(long, value_name = "URL")]
+    login_server: Option<Url>,
+
+    /// 小爱服务的 API 服务器，用于其他区域的账号（默认 https://api2.mina.mi.com/）
+    #[arg(long, value_name = "URL")]
+    api_server: Option<Url>,
+
+    /// 请求超时时间（秒）
+    #[arg(long, value_name = "SECONDS")]
+    timeout: Option<u64>,
+
+    /// 登录时使用的 User-Agent，登录被风控拦截时可以换一个再试
+    #[arg(long, value_name = "UA")]
+    login_user_agent: Option<String>,
+
+    /// HTTP 代理服务器，如 http://127.0.0.1:7890，默认读取 HTTPS_PROXY 等环境变量
+    #[arg(long, value_name = "URL")]
+    proxy: Option<Url>,
+
+    /// 查询类请求失败后的最大重试次数，播报、播放等命令不会重试
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
+
+    /// 请求过于频繁被限流时，等待后自动重试一次
+    #[arg(long)]
+    retry_on_rate_limit: bool,
+}
+
+impl ClientArgs {
+    fn apply_login(&self, mut login: Login) -> anyhow::Result<Login> {
+        if self.insecure {
+            login = login.danger_accept_invalid_certs(true)?;
+        }
+        if let Some(version) = &self.app_version {
+            login = login.app_version(version)?;
+        }
+        if let Some(server) = &self.login_server {
+            login = login.with_server(server.clone())?;
+        }
+        if let Some(server) = &self.api_server {
+            login = login.with_api_server(server.clone());
+        }
+        if let Some(proxy) = self.proxy()? {
+            login = login.with_proxy(proxy)?;
+        }
+        if let Some(timeout) = self.timeout {
+            login = login.timeout(Duration::from_secs(timeout))?;
+        }
+        if let Some(user_agent) = &self.login_user_agent {
+            login = login.user_agent(user_agent)?;
+        }
+
+        Ok(login)
+    }
+
+    fn apply(&self, mut xiaoai: Xiaoai) -> anyhow::Result<Xiaoai> {
+        if self.insecure {
+            xiaoai = xiaoai.danger_accept_invalid_certs(true)?;
+        }
+        if let Some(version) = &self.app_version {
+            xiaoai = xiaoai.app_version(version)?;
+        }
+        if let Some(server) = &self.api_server {
+            xiaoai = xiaoai.with_server(server.clone());
+        }
+        if let Some(timeout) = self.timeout {
+            xiaoai = xiaoai.timeout(Duration::from_secs(timeout))?;
+        }
+        if let Some(proxy) = self.proxy()? {
+            xiaoai = xiaoai.with_proxy(proxy)?;
+        }
+
+        Ok(xiaoai
+            .max_retries(self.retries)
+            .retry_on_rate_limit(self.retry_on_rate_limit))
+    }
+
+    fn proxy(&self) -> anyhow::Result<Option<miai::Proxy>> {
+        self.proxy
+            .as_ref()
+            .map(|url| miai::Proxy::all(url.as_str()).with_context(|| format!("无效的代理 {}", url)))
+            .transpose()
+    }
+}
+
+/// 输出格式
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    /// 纯文本
+    Text,
+    /// 对齐的表格
+    Table,
+    /// JSON
+    Json,
 }
 
 #[derive(Subcommand)]
 enum Commands {
     /// 登录以获得认证
-    Login,
+    Login {
+        /// 只验证账号密码是否可用，不保存认证文件
+        #[arg(long)]
+        check: bool,
+        /// 只保存未过期且持久化的 Cookies（若 serviceToken 是会话 Cookie 将无法使用）
+        #[arg(long, conflicts_with = "check")]
+        persistent_only: bool,
+        /// 使用已有的 Cookie 登录，形如 "userId=xxx; serviceToken=yyy"，不再输入账号密码
+        #[arg(long, env = "XIAOAI_COOKIE", hide_env_values = true)]
+        cookie: Option<String>,
+        /// 账号，指定后不再交互式输入；密码依次取自 --password-stdin、环境变量 MI_PASS，都没有时才提示输入
+        #[arg(long, env = "MI_USER", conflicts_with = "cookie")]
+        username: Option<String>,
+        /// 从标准输入的第一行读取密码，避免密码出现在命令行参数中
+        #[arg(long, requires = "username")]
+        password_stdin: bool,
+    },
+    /// 查看当前登录的账号
+    Whoami,
+    /// 查看登录状态的过期时间
+    Session,
     /// 列出设备
-    Device,
+    Device {
+        /// 以 master 模式请求设备列表，多房间、分组用户可以拿到更完整的信息
+        #[arg(long)]
+        master: bool,
+    },
+    /// 列出已知机型及其特性
+    Models,
+    /// 导出所有设备的原始信息与播放状态为 JSON，便于备份或反馈问题
+    Export {
+        /// 不隐去设备 ID、序列号、token 等敏感字段
+        #[arg(long)]
+        no_redact: bool,
+    },
     /// 播报文本
-    Say { text: String },
+    Say {
+        /// 播报的文本，为 `-` 时从标准输入读取并分段朗读
+        #[arg(required_unless_present = "file")]
+        text: Option<String>,
+        /// 从文件读取文本，按句子分段朗读
+        #[arg(long, conflicts_with = "text", value_hint = ValueHint::FilePath)]
+        file: Option<PathBuf>,
+        /// 语速，1.0 为正常语速，部分机型不支持
+        #[arg(long)]
+        speed: Option<f32>,
+        /// 音色，部分机型不支持
+        #[arg(long)]
+        speaker: Option<String>,
+    },
     /// 播放
     Play {
         /// 可选的音乐链接
         url: Option<Url>,
+        /// 播放本地音频文件，会在局域网内临时提供该文件直到播放结束
+        #[arg(long, conflicts_with = "url")]
+        file: Option<PathBuf>,
+    },
+    /// 播放内置的网络电台
+    Radio {
+        /// 电台的标识或名称，使用 `list` 列出所有内置电台
+        name: String,
     },
     /// 暂停
     Pause,
     /// 停止
     Stop,
+    /// 在播放和暂停之间切换，输出切换后的状态
+    Toggle,
+    /// 下一首
+    Next,
+    /// 上一首
+    Prev,
+    /// 设置循环模式
+    Loop {
+        #[arg(value_enum)]
+        mode: LoopArg,
+    },
     /// 调整音量
-    Volume { volume: u32 },
+    #[command(args_conflicts_with_subcommands = true)]
+    Volume {
+        /// 目标音量，可以用 +10、-10 这样的写法相对当前音量调整
+        #[arg(allow_negative_numbers = true)]
+        volume: Option<VolumeValue>,
+        #[command(subcommand)]
+        step: Option<VolumeStep>,
+    },
+    /// 查看当前音量
+    VolumeGet,
+    /// 静音，记住静音前的音量
+    Mute,
+    /// 取消静音，恢复静音前的音量
+    Unmute {
+        /// 恢复到指定的音量，不指定时使用 mute 记住的音量
+        volume: Option<u32>,
+    },
+    /// 在一段时间内平滑调整音量，如睡前渐弱
+    Fade {
+        /// 目标音量
+        #[arg(long)]
+        to: u32,
+        /// 持续的秒数
+        #[arg(long, default_value_t = 30)]
+        secs: u64,
+        /// 分几步调整，默认每秒一步
+        #[arg(long)]
+        steps: Option<u32>,
+    },
     /// 询问
-    Ask { text: String },
-    /// 获取播放状态与最近对话文本
-    Status,
+    Ask {
+        text: String,
+        /// 只执行指令，小爱不出声回应
+        #[arg(long)]
+        silent: bool,
+        /// 等待并输出小爱的回答和识别出的意图
+        #[arg(long, conflicts_with = "silent")]
+        wait: bool,
+    },
+    /// 获取播放状态、音量和当前曲目
+    Status {
+        /// 输出原始 JSON，同 --json
+        #[arg(long)]
+        raw: bool,
+    },
+    /// 管理闹钟
+    Alarm {
+        #[command(subcommand)]
+        action: AlarmAction,
+    },
+    /// 按顺序执行 JSON 脚本中的命令，每一步的格式和 WebSocket API 的请求相同
+    Run {
+        /// 脚本文件
+        #[arg(value_hint = ValueHint::FilePath)]
+        script: PathBuf,
+        /// 某一步失败后继续执行后面的步骤
+        #[arg(long)]
+        continue_on_error: bool,
+    },
+    /// 查看配置文件
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// 开启或关闭勿扰模式
+    Dnd {
+        #[command(subcommand)]
+        action: DndAction,
+    },
+    /// 切换或询问输入源
+    Source {
+        #[command(subcommand)]
+        action: SourceAction,
+    },
+    /// 定时停止播放，如 `sleep 30m`
+    Sleep {
+        /// 多久后停止播放，如 30m、1h30m、90s，不带单位时按分钟计算
+        #[arg(value_parser = parse_duration, required_unless_present_any = ["cancel", "query"])]
+        after: Option<Duration>,
+        /// 取消定时关闭
+        #[arg(long, conflicts_with_all = ["after", "query"])]
+        cancel: bool,
+        /// 询问定时关闭的剩余时间
+        #[arg(long, conflicts_with = "after")]
+        query: bool,
+        /// 在本地计时，到时后停止播放，用于不支持语音定时关闭的设备；需要保持命令运行
+        #[arg(long, requires = "after", conflicts_with_all = ["cancel", "query"])]
+        local: bool,
+    },
     /// 监听关键词并触发回调（使用配置文件）
     Check,
     /// 启动 WebSocket API 服务器
-    Wsapi,
+    Wsapi {
+        /// 监听指定设备的关键词，可以多次指定
+        #[arg(long, value_name = "DEVICE_ID", conflicts_with = "all")]
+        watch: Vec<String>,
+        /// 监听所有设备的关键词
+        #[arg(long)]
+        all: bool,
+    },
+    /// 进入交互模式，保持登录状态和当前设备，连续执行多条命令
+    Repl,
+    /// 生成 shell 自动补全脚本，输出到标准输出
+    Completion {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// 逐项测试设备的常用功能，生成能力报告
+    Diagnose {
+        /// 允许执行会改变设备状态的测试（临时调低音量并播报）
+        #[arg(long)]
+        yes: bool,
+    },
+}
+
+/// 循环模式
+#[derive(Clone, Copy, ValueEnum)]
+enum LoopArg {
+    /// 顺序播放
+    Sequence,
+    /// 单曲循环
+    Single,
+    /// 列表循环
+    List,
+    /// 随机播放
+    Shuffle,
+}
+
+impl From<LoopArg> for LoopMode {
+    fn from(mode: LoopArg) -> Self {
+        match mode {
+            LoopArg::Sequence => LoopMode::Sequence,
+            LoopArg::Single => LoopMode::SingleLoop,
+            LoopArg::List => LoopMode::ListLoop,
+            LoopArg::Shuffle => LoopMode::Shuffle,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum VolumeStep {
+    /// 调高音量
+    Up {
+        /// 调整的幅度，默认使用配置文件中的 volume_step
+        #[arg(long)]
+        step: Option<u32>,
+    },
+    /// 调低音量
+    Down {
+        /// 调整的幅度，默认使用配置文件中的 volume_step
+        #[arg(long)]
+        step: Option<u32>,
+    },
+}
+
+#[derive(Subcommand)]
+enum AlarmAction {
+    /// 设置闹钟，小爱会播报设置的结果
+    Add {
+        /// 响铃时间，24 小时制，如 7:30
+        #[arg(value_parser = parse_time)]
+        time: Time,
+        /// 重复规则
+        #[arg(long, value_enum, default_value_t = RepeatArg::Once)]
+        repeat: RepeatArg,
+        /// 响铃时播报的提醒内容
+        #[arg(long)]
+        content: Option<String>,
+    },
+    /// 询问小爱设置了哪些闹钟，输出小爱的回答
+    List,
+    /// 按时间删除闹钟，小爱会播报删除的结果
+    Del {
+        /// 要删除的闹钟的时间，如 7:30
+        #[arg(value_parser = parse_time, required_unless_present = "all")]
+        time: Option<Time>,
+        /// 要删除的闹钟的重复规则
+        #[arg(long, value_enum, default_value_t = RepeatArg::Once)]
+        repeat: RepeatArg,
+        /// 删除所有闹钟
+        #[arg(long, conflicts_with_all = ["time", "repeat"])]
+        all: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// 打印当前使用的配置文件路径
+    Path,
+}
+
+#[derive(Subcommand)]
+enum DndAction {
+    /// 开启勿扰模式，可以同时设置时间段
+    On {
+        /// 勿扰开始的时间，24 小时制，如 23:00
+        #[arg(long, value_parser = parse_time, requires = "to")]
+        from: Option<Time>,
+        /// 勿扰结束的时间，如 7:00
+        #[arg(long, value_parser = parse_time, requires = "from")]
+        to: Option<Time>,
+    },
+    /// 关闭勿扰模式
+    Off,
+    /// 询问小爱勿扰模式的状态，输出小爱的回答
+    Status,
+}
+
+#[derive(Subcommand)]
+enum SourceAction {
+    /// 询问小爱当前的输入源，输出小爱的回答
+    Get,
+    /// 切换输入源，小爱会播报切换的结果
+    Set {
+        #[arg(value_enum)]
+        source: SourceArg,
+    },
+}
+
+/// 输入源
+#[derive(Clone, Copy, ValueEnum)]
+enum SourceArg {
+    /// 音箱自身的播放器
+    Internal,
+    /// 作为蓝牙音箱使用
+    Bluetooth,
+}
+
+impl From<SourceArg> for Source {
+    fn from(source: SourceArg) -> Self {
+        match source {
+            SourceArg::Internal => Source::Internal,
+            SourceArg::Bluetooth => Source::Bluetooth,
+        }
+    }
+}
+
+/// 闹钟的重复规则
+#[derive(Clone, Copy, ValueEnum)]
+enum RepeatArg {
+    /// 只响一次
+    Once,
+    /// 每天
+    Daily,
+    /// 每个工作日
+    Weekdays,
+    /// 每个周末
+    Weekends,
+}
+
+impl From<RepeatArg> for AlarmRepeat {
+    fn from(repeat: RepeatArg) -> Self {
+        match repeat {
+            RepeatArg::Once => AlarmRepeat::Once,
+            RepeatArg::Daily => AlarmRepeat::Daily,
+            RepeatArg::Weekdays => AlarmRepeat::Weekdays,
+            RepeatArg::Weekends => AlarmRepeat::Weekends,
+        }
+    }
+}
+
+/// 解析 `时:分` 格式的时间，用于闹钟和勿扰时段，取值范围由 [`Time::new`] 检查。
+fn parse_time(s: &str) -> Result<Time, String> {
+    let invalid = || format!("无效的时间 {}，应为 7:30 这样的 24 小时制时间", s);
+    let (hour, minute) = s.split_once([':', '：']).ok_or_else(invalid)?;
+    let hour = hour.trim().parse().map_err(|_| invalid())?;
+    let minute = minute.trim().parse().map_err(|_| invalid())?;
+
+    Time::new(hour, minute).map_err(|err| err.to_string())
+}
+
+/// 解析 `30m`、`1h30m`、`90s` 这样的时长，不带单位时按分钟计算。
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("无效的时长 {}，应为 30m、1h30m 或 90s 这样的时长", s);
+    if let Ok(minutes) = s.parse::<u64>() {
+        let secs = minutes.checked_mul(60).filter(|&secs| secs > 0).ok_or_else(invalid)?;
+        return Ok(Duration::from_secs(secs));
+    }
+
+    let mut secs: u64 = 0;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' | 'H' => 3600,
+            'm' | 'M' => 60,
+            's' | 'S' => 1,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = std::mem::take(&mut number).parse().map_err(|_| invalid())?;
+        secs = value
+            .checked_mul(unit)
+            .and_then(|value| secs.checked_add(value))
+            .ok_or_else(invalid)?;
+    }
+    if !number.is_empty() || secs == 0 {
+        return Err(invalid());
+    }
+
+    Ok(Duration::from_secs(secs))
+}
+
+/// `volume` 命令的目标音量。
+#[derive(Clone, Copy)]
+enum VolumeValue {
+    /// 设为指定的音量
+    Absolute(u32),
+    /// 相对当前音量调整
+    Relative(i32),
+}
+
+impl FromStr for VolumeValue {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |_| format!("无效的音量 {}，应为 30、+10 或 -10 这样的整数", s);
+        if s.starts_with(['+', '-']) {
+            s.parse().map(VolumeValue::Relative).map_err(invalid)
+        } else {
+            s.parse().map(VolumeValue::Absolute).map_err(invalid)
+        }
+    }
+}
+
+/// 检查音量是否在 [`miai::model::VOLUME_RANGE`] 之内。
+fn check_volume(volume: u32) -> anyhow::Result<()> {
+    let range = miai::model::VOLUME_RANGE;
+    ensure!(
+        range.contains(&volume),
+        "音量 {} 超出范围 {}..={}",
+        volume,
+        range.start(),
+        range.end()
+    );
+    Ok(())
+}
+
+impl VolumeStep {
+    /// 计算音量的变化量，未指定幅度时使用 `default_step`。
+    fn delta(&self, default_step: u32) -> i32 {
+        match self {
+            VolumeStep::Up { step } => step.unwrap_or(default_step) as i32,
+            VolumeStep::Down { step } => -(step.unwrap_or(default_step) as i32),
+        }
+    }
 }
 
 impl Cli {
+    /// 实际使用的输出格式。
+    fn output(&self) -> OutputFormat {
+        if self.json {
+            OutputFormat::Json
+        } else {
+            self.output
+        }
+    }
+
+    /// 需要同时执行命令的多台设备，只指定了一台设备时返回 `None`。
+    async fn broadcast_targets(&self, xiaoai: &Xiaoai) -> anyhow::Result<Option<Vec<DeviceInfo>>> {
+        let ids: Option<Vec<&str>> = self
+            .device_id
+            .as_deref()
+            .filter(|ids| ids.contains(','))
+            .map(|ids| ids.split(',').map(str::trim).filter(|id| !id.is_empty()).collect());
+        if !self.all_devices && ids.is_none() {
+            return Ok(None);
+        }
+
+        let devices = xiaoai
+            .device_info_cached(DEVICE_CACHE_TTL)
+            .await
+            .context("获取设备列表失败")?;
+        let Some(ids) = ids else {
+            ensure!(!devices.is_empty(), "无可用设备，需要在小米音箱 APP 中绑定");
+            return Ok(Some(devices));
+        };
+
+        ids.into_iter()
+            .map(|id| {
+                devices
+                    .iter()
+                    .find(|d| d.device_id == id)
+                    .cloned()
+                    .with_context(|| format!("找不到设备 {}", id))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Some)
+    }
+
+    /// 命令行指定的心跳间隔。
+    fn heartbeat_interval(&self) -> Option<Duration> {
+        self.heartbeat.map(Duration::from_secs)
+    }
+
+    /// 用配置文件中的 `device_id`、`auth_file`、`server`、`proxy` 填充命令行没有指定的参数。
+    ///
+    /// 命令行参数优先于配置文件，配置文件不存在时什么也不做。配置文件有误时只输出警告，
+    /// 不影响 `login` 等不读取配置的命令，读取配置的命令会在读取时报错。
+    fn apply_config(&mut self, matches: &ArgMatches) {
+        if !self.config_file.exists() {
+            return;
+        }
+        let config = File::open(&self.config_file)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| Ok(serde_json::from_reader::<_, Config>(BufReader::new(file))?));
+        let config = match config {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("⚠️  无法解析配置文件 {}，忽略其中的默认设置: {}", self.config_file.display(), e);
+                return;
+            }
+        };
+
+        let device_given = self.device_id.is_some() || self.device_name.is_some() || self.all_devices;
+        if !device_given && !config.device_id.is_empty() {
+            self.device_id = Some(config.device_id);
+        }
+        if let Some(auth_file) = config.auth_file {
+            if matches.value_source("auth_file") == Some(ValueSource::DefaultValue) {
+                let dir = self.config_file.parent().unwrap_or(Path::new(""));
+                self.auth_file = dir.join(auth_file);
+            }
+        }
+        if let Some(server) = config.server.filter(|_| self.client.api_server.is_none()) {
+            match Url::parse(&server) {
+                Ok(server) => self.client.api_server = Some(server),
+                Err(_) => eprintln!("⚠️  配置文件中的 server 不是有效的 URL，已忽略: {}", server),
+            }
+        }
+        if let Some(proxy) = config.proxy.filter(|_| self.client.proxy.is_none()) {
+            match Url::parse(&proxy) {
+                Ok(proxy) => self.client.proxy = Some(proxy),
+                Err(_) => eprintln!("⚠️  配置文件中的 proxy 不是有效的 URL，已忽略: {}", proxy),
+            }
+        }
+    }
+
+    /// 读取配置文件中的音量调整幅度，配置文件不存在时使用默认值。
+    fn volume_step(&self) -> anyhow::Result<u32> {
+        if !self.config_file.exists() {
+            return Ok(default_volume_step());
+        }
+
+        let config_file = File::open(&self.config_file)?;
+        let config: Config = serde_json::from_reader(BufReader::new(config_file))?;
+        Ok(config.volume_step)
+    }
+
     fn xiaoai(&self) -> anyhow::Result<Xiaoai> {
-        let file = File::open(&self.auth_file)
-            .with_context(|| format!("需要可用的认证文件 {}", self.auth_file.display()))?;
+        load_xiaoai(&self.auth_file, self)
+    }
 
-        Xiaoai::load(BufReader::new(file))
-            .map_err(anyhow::Error::from_boxed)
-            .with_context(|| format!("加载认证文件 {} 失败", self.auth_file.display()))
+    /// 保存静音前音量的文件，和认证文件放在同一目录。
+    fn mute_file(&self) -> PathBuf {
+        self.auth_file.with_file_name("xiaoai-mute.json")
     }
 
     /// 获取用户指定的设备 ID。
     ///
+    /// 如果用户在命令行指定了设备名称，会在设备列表中按名称查找。
     /// 如果用户没有在命令行指定，则会向服务器请求设备列表。
     /// 如果请求结果只有一个设备，会自动选择这个唯一的设备。
     /// 如果请求结果存在多个设备，则会让用户自行选择。
@@ -300,8 +1515,15 @@ impl Cli {
         if let Some(device_id) = &self.device_id {
             return Ok(device_id.into());
         }
+        if let Some(name) = &self.device_name {
+            let device = xiaoai.device_by_name(name).await?;
+            return Ok(device.device_id.into());
+        }
 
-        let info = xiaoai.device_info().await.context("获取设备列表失败")?;
+        let info = xiaoai
+            .device_info_cached(DEVICE_CACHE_TTL)
+            .await
+            .context("获取设备列表失败")?;
         ensure!(!info.is_empty(), "无可用设备，需要在小米音箱 APP 中绑定");
         if info.len() == 1 {
             return Ok(info[0].device_id.clone().into());
@@ -314,12 +1536,498 @@ impl Cli {
     }
 }
 
+/// 对多台设备并发执行同一命令，逐台打印结果。
+///
+/// 只支持 [`is_simple`] 的命令。有设备执行失败时整体返回错误。
+async fn broadcast(xiaoai: &Xiaoai, cli: &Cli, devices: Vec<DeviceInfo>) -> anyhow::Result<()> {
+    ensure!(
+        !matches!(&cli.command, Commands::Say { text: Some(text), .. } if text == "-"),
+        "同时对多台设备播报时不支持从标准输入读取"
+    );
+    ensure!(is_simple(&cli.command), "该命令不支持同时对多台设备执行");
+
+    let results = join_all(
+        devices
+            .iter()
+            .map(|device| send_command(xiaoai, cli, &cli.command, &device.device_id)),
+    )
+    .await;
+
+    let json = cli.output() == OutputFormat::Json;
+    let mut outputs = Vec::with_capacity(devices.len());
+    let mut failed = 0;
+    for (device, result) in devices.iter().zip(results) {
+        match result {
+            Ok(response) if json => outputs.push(serde_json::json!({
+                "device_id": device.device_id,
+                "code": response.code,
+                "message": response.message,
+                "data": response.data,
+            })),
+            Ok(response) => println!(
+                "{} ({}): code: {}, message: {}",
+                device.name, device.device_id, response.code, response.message
+            ),
+            Err(e) => {
+                failed += 1;
+                if json {
+                    outputs.push(serde_json::json!({
+                        "device_id": device.device_id,
+                        "error": format!("{:#}", e),
+                    }));
+                } else {
+                    println!("{} ({}): 失败: {:#}", device.name, device.device_id, e);
+                }
+            }
+        }
+    }
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&outputs)?);
+        if failed > 0 {
+            // 结果已经输出，不再额外输出错误
+            return Err(AlreadyReported.into());
+        }
+    }
+    ensure!(failed == 0, "{} 台设备中有 {} 台执行失败", devices.len(), failed);
+
+    Ok(())
+}
+
+/// 是否是发送一次请求、只需要打印响应的命令，这些命令由 [`send_command`] 执行，
+/// 单台设备和 [`broadcast`] 共用。
+fn is_simple(command: &Commands) -> bool {
+    match command {
+        Commands::Say { text: Some(text), file: None, .. } => text != "-",
+        Commands::Play { file: None, .. }
+        | Commands::Radio { .. }
+        | Commands::Pause
+        | Commands::Stop
+        | Commands::Next
+        | Commands::Prev
+        | Commands::Loop { .. }
+        | Commands::Volume { .. }
+        | Commands::Fade { .. }
+        | Commands::Ask { wait: false, .. }
+        | Commands::Dnd { action: DndAction::On { .. } | DndAction::Off }
+        | Commands::Source { action: SourceAction::Set { .. } }
+        | Commands::Sleep { cancel: true, .. }
+        | Commands::Alarm { action: AlarmAction::Add { .. } | AlarmAction::Del { .. } } => true,
+        Commands::Sleep { query, local, .. } => !query && !local,
+        _ => false,
+    }
+}
+
+/// 对单台设备执行 [`is_simple`] 的命令，返回小爱的响应。
+async fn send_command(
+    xiaoai: &Xiaoai,
+    cli: &Cli,
+    command: &Commands,
+    device_id: &str,
+) -> anyhow::Result<XiaoaiResponse> {
+    let response = match command {
+        Commands::Say { text: Some(text), speed, speaker, .. } => {
+            let options = TtsOptions {
+                speed: *speed,
+                speaker: speaker.clone(),
+            };
+            xiaoai.tts_with(device_id, text, &options).await?
+        }
+        Commands::Play { url: Some(url), .. } => {
+            let devices = xiaoai
+                .device_info_cached(DEVICE_CACHE_TTL)
+                .await
+                .context("获取设备列表失败")?;
+            match devices.iter().find(|d| d.device_id == device_id) {
+                Some(device) => xiaoai.play_url_for(device, url.as_str()).await?,
+                None => xiaoai.play_url(device_id, url.as_str()).await?,
+            }
+        }
+        Commands::Play { url: None, .. } => xiaoai.set_play_state(device_id, PlayState::Play).await?,
+        Commands::Radio { name } => xiaoai.play_station(device_id, name).await?,
+        Commands::Pause => xiaoai.set_play_state(device_id, PlayState::Pause).await?,
+        Commands::Stop => xiaoai.set_play_state(device_id, PlayState::Stop).await?,
+        Commands::Next => xiaoai.next_track(device_id).await?,
+        Commands::Prev => xiaoai.prev_track(device_id).await?,
+        Commands::Loop { mode } => xiaoai.set_loop_mode(device_id, (*mode).into()).await?,
+        Commands::Volume { volume: Some(VolumeValue::Absolute(volume)), .. } => {
+            check_volume(*volume)?;
+            xiaoai.set_volume(device_id, *volume).await?
+        }
+        Commands::Volume { volume: Some(VolumeValue::Relative(delta)), .. } => {
+            xiaoai.adjust_volume(device_id, *delta).await?
+        }
+        Commands::Volume { volume: None, step: Some(step) } => {
+            xiaoai.adjust_volume(device_id, step.delta(cli.volume_step()?)).await?
+        }
+        Commands::Volume { volume: None, step: None } => bail!("需要指定音量，或使用 up/down 调整"),
+        Commands::Fade { to, secs, steps } => fade(xiaoai, device_id, *to, *secs, *steps).await?,
+        Commands::Ask { text, silent, .. } => xiaoai.nlp_with(device_id, text, !silent).await?,
+        Commands::Dnd { action } => {
+            let (enabled, from, to) = match action {
+                DndAction::On { from, to } => (true, *from, *to),
+                _ => (false, None, None),
+            };
+            xiaoai.set_do_not_disturb(device_id, enabled, from, to).await?
+        }
+        Commands::Source { action: SourceAction::Set { source } } => {
+            xiaoai.set_source(device_id, (*source).into()).await?
+        }
+        Commands::Sleep { cancel: true, .. } => xiaoai.cancel_sleep_timer(device_id).await?,
+        Commands::Sleep { after: Some(after), .. } => xiaoai.set_sleep_timer(device_id, *after).await?,
+        Commands::Alarm { action: AlarmAction::Add { time, repeat, content } } => {
+            let mut spec = AlarmSpec::at(*time).repeat((*repeat).into());
+            if let Some(content) = content {
+                spec = spec.content(content);
+            }
+            xiaoai.create_alarm(device_id, &spec).await?
+        }
+        Commands::Alarm { action: AlarmAction::Del { all: true, .. } } => {
+            xiaoai.delete_all_alarms(device_id).await?
+        }
+        Commands::Alarm { action: AlarmAction::Del { time: Some(time), repeat, .. } } => {
+            let spec = AlarmSpec::at(*time).repeat((*repeat).into());
+            xiaoai.delete_alarm(device_id, &spec).await?
+        }
+        _ => bail!("该命令不支持同时对多台设备执行"),
+    };
+
+    Ok(response)
+}
+
+/// 默认的配置文件路径。
+///
+/// 优先使用当前目录的配置文件，不存在时使用 `$XDG_CONFIG_HOME/xiaoai/` 下的配置文件，
+/// 未设置 `XDG_CONFIG_HOME` 时使用 `~/.config`。两者都不存在时仍返回当前目录的路径。
+fn default_config_file() -> PathBuf {
+    let local = PathBuf::from(DEFAULT_CONFIG_FILE);
+    if local.exists() {
+        return local;
+    }
+
+    env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("xiaoai").join(DEFAULT_CONFIG_FILE))
+        .filter(|path| path.exists())
+        .unwrap_or(local)
+}
+
+/// 从认证文件加载登录状态。
+fn load_xiaoai(auth_file: &Path, cli: &Cli) -> anyhow::Result<Xiaoai> {
+    let file = File::open(auth_file)
+        .with_context(|| format!("需要可用的认证文件 {}", auth_file.display()))?;
+    let reader = BufReader::new(file);
+
+    let xiaoai = match &cli.passphrase {
+        Some(passphrase) => Xiaoai::load_encrypted(reader, passphrase),
+        None => Xiaoai::load(reader),
+    }
+    .with_context(|| format!("加载认证文件 {} 失败", auth_file.display()))?;
+
+    cli.client.apply(xiaoai)
+}
+
+/// 把登录状态写回认证文件，指定了 `passphrase` 时加密保存。
+fn save_xiaoai(xiaoai: &Xiaoai, auth_file: &Path, passphrase: Option<&str>) -> anyhow::Result<()> {
+    let mut file = File::create(auth_file)
+        .with_context(|| format!("无法写入认证文件 {}", auth_file.display()))?;
+    match passphrase {
+        Some(passphrase) => xiaoai.save_encrypted(&mut file, passphrase)?,
+        None => xiaoai.save(&mut file)?,
+    }
+    Ok(())
+}
+
+/// 执行 `fade` 命令，未指定步数时每秒调整一次。
+async fn fade(
+    xiaoai: &Xiaoai,
+    device_id: &str,
+    to: u32,
+    secs: u64,
+    steps: Option<u32>,
+) -> anyhow::Result<XiaoaiResponse> {
+    check_volume(to)?;
+    let steps = steps.unwrap_or_else(|| u32::try_from(secs).unwrap_or(u32::MAX));
+    eprintln!("🔉 {} 秒内分 {} 步调整到 {}", secs, steps, to);
+
+    Ok(xiaoai
+        .fade_volume(device_id, to, Duration::from_secs(secs), steps)
+        .await?)
+}
+
+/// `say` 从文件或标准输入读取的文本，直接在命令行给出文本时返回 `None`。
+fn say_input(text: Option<&str>, file: Option<&Path>) -> anyhow::Result<Option<String>> {
+    if let Some(file) = file {
+        let text = fs::read_to_string(file)
+            .with_context(|| format!("无法读取文件 {}", file.display()))?;
+        return Ok(Some(text));
+    }
+    if text == Some("-") {
+        let mut input = String::new();
+        io::stdin()
+            .read_to_string(&mut input)
+            .context("读取标准输入失败")?;
+        return Ok(Some(input));
+    }
+
+    Ok(None)
+}
+
+/// 播放状态的中文描述。
+fn play_state_text(state: &PlayState) -> String {
+    match state {
+        PlayState::Play => "播放中".to_string(),
+        PlayState::Pause => "已暂停".to_string(),
+        PlayState::Stop => "已停止".to_string(),
+        other => format!("{:?}", other),
+    }
+}
+
+/// 输出 [`NlpResult`] 中小爱的回答，没有回答时输出原始结果。
+fn print_answer(cli: &Cli, result: &NlpResult) -> anyhow::Result<()> {
+    if cli.output() == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(result)?);
+    } else if let Some(answer) = &result.answer {
+        println!("{}", answer);
+    } else {
+        println!("{}", serde_json::to_string_pretty(&result.raw)?);
+    }
+
+    Ok(())
+}
+
+/// 读取 `mute` 记住的各设备静音前的音量，文件不存在或无法解析时视为没有记录。
+fn read_muted_volumes(cli: &Cli) -> HashMap<String, u32> {
+    fs::read(cli.mute_file())
+        .ok()
+        .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+        .unwrap_or_default()
+}
+
+/// 从配置文件读取账号密码，配置文件不存在或没有填写时提示用户输入。
+fn read_credentials(config_file: &Path) -> anyhow::Result<(String, String)> {
+    let credentials = if config_file.exists() {
+        let config_file = File::open(config_file)?;
+        let config: Config = serde_json::from_reader(BufReader::new(config_file))?;
+        
+        if !config.username.is_empty() && !config.password.is_empty() {
+            eprintln!("使用配置文件中的凭据登录...");
+            (config.username, config.password)
+        } else {
+            // 配置文件存在但凭据为空，提示用户输入
+            let username = Text::new("账号:").prompt()?;
+            let password = prompt_password()?;
+            (username, password)
+        }
+    } else {
+        // 配置文件不存在，提示用户输入
+        let username = Text::new("账号:").prompt()?;
+        let password = prompt_password()?;
+        (username, password)
+    };
+
+    Ok(credentials)
+}
+
+/// 交互式输入密码。
+fn prompt_password() -> anyhow::Result<String> {
+    Ok(Password::new("密码:")
+        .with_display_toggle_enabled()
+        .with_display_mode(PasswordDisplayMode::Masked)
+        .without_confirmation()
+        .with_help_message("CTRL + R 显示/隐藏密码")
+        .prompt()?)
+}
+
+/// 通过 `--username` 登录时的密码，依次取自标准输入、环境变量 `MI_PASS`，都没有时提示输入。
+fn login_password(password_stdin: bool) -> anyhow::Result<String> {
+    if password_stdin {
+        let mut line = String::new();
+        io::stdin()
+            .read_line(&mut line)
+            .context("从标准输入读取密码失败")?;
+        let password = line.trim_end_matches(['\r', '\n']);
+        ensure!(!password.is_empty(), "标准输入中没有密码");
+        return Ok(password.to_string());
+    }
+    if let Some(password) = env::var("MI_PASS").ok().filter(|password| !password.is_empty()) {
+        return Ok(password);
+    }
+
+    prompt_password()
+}
+
+/// 分步登录，并在 stderr 打印当前步骤，便于定位登录失败的环节。
+async fn login_with_progress(
+    username: &str,
+    password: &str,
+    client: &ClientArgs,
+) -> anyhow::Result<Xiaoai> {
+    let login = client.apply_login(Login::new(username, password)?)?;
+    eprintln!("[1/3] 初步登录...");
+    let login_response = login.login().await.context("初步登录失败")?;
+    eprintln!("[2/3] 认证账号密码...");
+    let auth_response = match login.auth(login_response).await {
+        Err(miai::Error::NeedVerification { url }) => {
+            verify_login(&login, &url).await?;
+            return Ok(Xiaoai::from_login(login)?);
+        }
+        Err(miai::Error::NeedCaptcha { url }) => {
+            bail!("账号需要完成验证，请在浏览器中打开以下链接完成验证后重试:\n{}", url)
+        }
+        result => result.context("认证失败")?,
+    };
+    eprintln!("[3/3] 获取 serviceToken...");
+    login
+        .get_token(auth_response)
+        .await
+        .context("获取 serviceToken 失败")?;
+
+    Ok(Xiaoai::from_login(login)?)
+}
+
+/// 完成二次验证：发送验证码，并提示用户输入。
+async fn verify_login(login: &Login, url: &str) -> anyhow::Result<()> {
+    eprintln!("⚠️  账号需要二次验证");
+    eprintln!("如果收不到验证码，也可以在浏览器中打开以下链接完成验证后重新登录:\n{}", url);
+    let method = login
+        .start_verification(url)
+        .await
+        .context("发送验证码失败")?;
+    let target = match method {
+        VerifyMethod::Phone => "手机",
+        VerifyMethod::Email => "邮箱",
+        _ => "手机或邮箱",
+    };
+    let ticket = Text::new(&format!("验证码已发送到绑定的{}，请输入验证码:", target)).prompt()?;
+    eprintln!("[3/3] 提交验证码并获取 serviceToken...");
+    login
+        .resolve_verification(method, &ticket)
+        .await
+        .context("二次验证失败")
+}
+
 struct DisplayDeviceInfo(DeviceInfo);
 
 impl Display for DisplayDeviceInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "名称: {}", self.0.name)?;
         writeln!(f, "设备 ID: {}", self.0.device_id)?;
-        writeln!(f, "机型: {}", self.0.hardware)
+        writeln!(f, "机型: {}", self.0.hardware)?;
+        if let Some(group) = &self.0.group {
+            writeln!(f, "分组: {}", group)?;
+        }
+        let online = match self.0.is_online() {
+            Some(true) => "是",
+            Some(false) => "否",
+            None => "未知",
+        };
+        writeln!(f, "在线: {}", online)
+    }
+}
+
+/// 导出时需要隐去的字段名（小写比较），另外任何包含 `token` 的字段也会被隐去。
+const REDACTED_KEYS: &[&str] = &[
+    "deviceid",
+    "device_id",
+    "serialnumber",
+    "sn",
+    "mac",
+    "macaddress",
+    "miotdid",
+    "userid",
+    "ssid",
+    "bssid",
+    "ip",
+];
+
+/// 递归地隐去 JSON 中的敏感字段。
+fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if REDACTED_KEYS.contains(&key.as_str()) || key.contains("token") {
+                    *value = serde_json::Value::String("<redacted>".to_string());
+                } else {
+                    redact(value);
+                }
+            }
+        }
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("30"), Ok(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("2H5S"), Ok(Duration::from_secs(2 * 3600 + 5)));
+    }
+
+    #[test]
+    fn parse_invalid_durations() {
+        for s in ["0", "0m", "", "5x", "1h30", "h", "-5", "1.5h"] {
+            assert!(parse_duration(s).is_err(), "{} 应为无效的时长", s);
+        }
+    }
+
+    #[test]
+    fn parse_overflowing_durations() {
+        let max = u64::MAX.to_string();
+        for s in [max.clone(), format!("{}h", max), format!("{}s1s", max), format!("{}0s", max)] {
+            assert!(parse_duration(&s).is_err(), "{} 应为无效的时长", s);
+        }
+        assert_eq!(parse_duration(&format!("{}s", max)), Ok(Duration::from_secs(u64::MAX)));
+    }
+
+    #[test]
+    fn redact_sensitive_fields() {
+        let mut value = serde_json::json!({
+            "name": "客厅音箱",
+            "deviceID": "123",
+            "SerialNumber": "sn",
+            "serviceToken": "secret",
+            "miotDID": 456,
+            "extra": {
+                "MAC": "00:11:22:33:44:55",
+                "hardware": "LX06",
+                "network": {"ssid": "home", "IP": "192.168.1.2"},
+            },
+            "devices": [
+                {"device_id": "a", "alias": "卧室"},
+                {"accessTokenExpire": 1, "userId": 789},
+            ],
+        });
+
+        redact(&mut value);
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "name": "客厅音箱",
+                "deviceID": "<redacted>",
+                "SerialNumber": "<redacted>",
+                "serviceToken": "<redacted>",
+                "miotDID": "<redacted>",
+                "extra": {
+                    "MAC": "<redacted>",
+                    "hardware": "LX06",
+                    "network": {"ssid": "<redacted>", "IP": "<redacted>"},
+                },
+                "devices": [
+                    {"device_id": "<redacted>", "alias": "卧室"},
+                    {"accessTokenExpire": "<redacted>", "userId": "<redacted>"},
+                ],
+            })
+        );
     }
 }
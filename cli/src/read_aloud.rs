@@ -0,0 +1,145 @@
+//! `say --file` / `say -`：朗读一整段长文本。
+//!
+//! 单次 TTS 的文本长度有限，过长时设备会截断或直接报错。这里先按句子把文本拆成不超过
+//! [`MAX_SEGMENT_CHARS`] 个字符的片段，再逐段播报。设备不会告知播报何时结束，
+//! 所以按字数估算每段的朗读时间，等待后再播报下一段，避免后一段打断前一段。
+
+use std::time::Duration;
+
+use anyhow::Context;
+use miai::{TtsOptions, Xiaoai};
+
+/// 每段的最大字符数
+const MAX_SEGMENT_CHARS: usize = 100;
+
+/// 正常语速下每秒朗读的字符数，用于估算每段的朗读时间
+const CHARS_PER_SECOND: f32 = 4.0;
+
+/// 两段之间额外留出的间隔
+const SEGMENT_GAP: Duration = Duration::from_millis(800);
+
+/// 句子的结束符号，拆分时优先在这些符号之后断开
+const SENTENCE_ENDS: &[char] = &['。', '！', '？', '；', '…', '!', '?', ';', '\n'];
+
+/// 句子过长时的次要断点
+const CLAUSE_ENDS: &[char] = &['，', '、', '：', ',', ':'];
+
+/// 分段朗读 `text`，在 stderr 打印进度。
+pub async fn run(
+    xiaoai: &Xiaoai,
+    device_id: &str,
+    text: &str,
+    options: &TtsOptions,
+) -> anyhow::Result<()> {
+    let segments = split(text, MAX_SEGMENT_CHARS);
+    anyhow::ensure!(!segments.is_empty(), "没有需要朗读的文本");
+    eprintln!(
+        "共 {} 个字符，按句子分为 {} 段，每段不超过 {} 个字符",
+        text.chars().count(),
+        segments.len(),
+        MAX_SEGMENT_CHARS
+    );
+
+    let speed = options.speed.filter(|speed| *speed > 0.0).unwrap_or(1.0);
+    for (i, segment) in segments.iter().enumerate() {
+        eprintln!("[{}/{}] {}", i + 1, segments.len(), segment);
+        xiaoai
+            .tts_with(device_id, segment, options)
+            .await
+            .with_context(|| format!("播报第 {} 段失败", i + 1))?;
+
+        if i + 1 < segments.len() {
+            let seconds = segment.chars().count() as f32 / CHARS_PER_SECOND / speed;
+            tokio::time::sleep(Duration::from_secs_f32(seconds) + SEGMENT_GAP).await;
+        }
+    }
+    eprintln!("✅ 朗读完成");
+
+    Ok(())
+}
+
+/// 按句子拆分文本，相邻的短句会合并，每段不超过 `max_chars` 个字符。
+///
+/// 单个句子过长时先在逗号等处断开，仍然过长时按字符数硬性截断。
+fn split(text: &str, max_chars: usize) -> Vec<String> {
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut current_chars = 0;
+
+    let pieces = text
+        .split_inclusive(SENTENCE_ENDS)
+        .flat_map(|sentence| {
+            if sentence.chars().count() <= max_chars {
+                vec![sentence]
+            } else {
+                sentence.split_inclusive(CLAUSE_ENDS).collect()
+            }
+        })
+        .flat_map(|piece| chunk_chars(piece, max_chars));
+
+    for piece in pieces {
+        let chars = piece.chars().count();
+        if current_chars + chars > max_chars {
+            segments.extend(finish(&mut current));
+            current_chars = 0;
+        }
+        current.push_str(piece);
+        current_chars += chars;
+    }
+    segments.extend(finish(&mut current));
+
+    segments
+}
+
+/// 按字符数截断过长的片段
+fn chunk_chars(text: &str, max_chars: usize) -> Vec<&str> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while let Some((end, _)) = rest.char_indices().nth(max_chars) {
+        let (chunk, tail) = rest.split_at(end);
+        chunks.push(chunk);
+        rest = tail;
+    }
+    chunks.push(rest);
+    chunks
+}
+
+/// 取出当前累积的一段，只有空白时丢弃
+fn finish(current: &mut String) -> Option<String> {
+    let segment = std::mem::take(current);
+    let segment = segment.trim();
+    (!segment.is_empty()).then(|| segment.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_on_cjk_punctuation() {
+        assert_eq!(
+            split("你好。今天天气不错！出门吗？", 7),
+            ["你好。", "今天天气不错！", "出门吗？"]
+        );
+        assert_eq!(
+            split("你好。今天天气不错！出门吗？", 100),
+            ["你好。今天天气不错！出门吗？"]
+        );
+    }
+
+    #[test]
+    fn split_long_sentence() {
+        let text = "一二三四五，六七八九十一二三四五六七八九十。";
+        let segments = split(text, 5);
+
+        assert!(segments.iter().all(|segment| segment.chars().count() <= 5));
+        assert_eq!(segments.concat(), text);
+        assert_eq!(segments[0], "一二三四五");
+    }
+
+    #[test]
+    fn split_whitespace_only() {
+        assert!(split("  \n \n", 100).is_empty());
+        assert!(split("", 100).is_empty());
+    }
+}
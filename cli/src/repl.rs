@@ -0,0 +1,207 @@
+//! `repl` 命令：保持登录状态和当前设备，连续执行多条命令。
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use inquire::{InquireError, Text};
+use miai::Xiaoai;
+
+use crate::{Cli, Commands, DEVICE_CACHE_TTL, run_command};
+
+/// REPL 中的一行输入，和命令行使用同样的子命令。
+#[derive(Parser)]
+#[command(no_binary_name = true, name = "", disable_version_flag = true)]
+struct Line {
+    #[command(subcommand)]
+    command: Commands,
+}
+
+pub async fn run(xiaoai: &Xiaoai, cli: &Cli) -> Result<()> {
+    let mut device_id = cli.device_id(xiaoai).await?.into_owned();
+    let mut prompt = device_name(xiaoai, &device_id).await;
+
+    eprintln!("输入命令执行，如 say 你好、volume 30、pause");
+    eprintln!("use <设备名称或 ID> 切换设备，help 查看所有命令，exit 退出\n");
+
+    loop {
+        let input = match Text::new(&format!("{}>", prompt)).prompt() {
+            Ok(input) => input,
+            Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let args = match split_args(&input) {
+            Ok(args) => args,
+            Err(e) => {
+                eprintln!("{}", e);
+                continue;
+            }
+        };
+
+        match args.first().map(String::as_str) {
+            None => continue,
+            Some("exit" | "quit") => break,
+            Some("use") => {
+                let Some(target) = args.get(1) else {
+                    eprintln!("用法: use <设备名称或 ID>");
+                    continue;
+                };
+                match find_device(xiaoai, target).await {
+                    Ok(id) => {
+                        prompt = device_name(xiaoai, &id).await;
+                        device_id = id;
+                    }
+                    Err(e) => eprintln!("❌ {:#}", e),
+                }
+                continue;
+            }
+            Some(_) => {}
+        }
+
+        let line = match Line::try_parse_from(&args) {
+            Ok(line) => line,
+            // help 和 --help 也会走到这里，由 clap 打印帮助
+            Err(e) => {
+                let _ = e.print();
+                continue;
+            }
+        };
+        if !supported(&line.command) {
+            eprintln!("REPL 中不支持该命令");
+            continue;
+        }
+        if let Err(e) = run_command(xiaoai, cli, &line.command, &device_id).await {
+            eprintln!("❌ {:#}", e);
+        }
+    }
+
+    Ok(())
+}
+
+/// 只支持针对单台设备、执行完就返回的命令，登录、列出设备等命令请退出后执行。
+///
+/// 从标准输入读取（`say -`）或需要一直运行（`check`、`diagnose`、`play --file`、`sleep --local`）
+/// 的命令会和 REPL 抢占终端，也不支持。
+fn supported(command: &Commands) -> bool {
+    match command {
+        Commands::Say { text, .. } => text.as_deref() != Some("-"),
+        Commands::Play { file, .. } => file.is_none(),
+        Commands::Sleep { local, .. } => !local,
+        Commands::Radio { .. }
+        | Commands::Pause
+        | Commands::Stop
+        | Commands::Toggle
+        | Commands::Next
+        | Commands::Prev
+        | Commands::Loop { .. }
+        | Commands::Volume { .. }
+        | Commands::VolumeGet
+        | Commands::Mute
+        | Commands::Unmute { .. }
+        | Commands::Fade { .. }
+        | Commands::Ask { .. }
+        | Commands::Status { .. }
+        | Commands::Alarm { .. }
+        | Commands::Dnd { .. }
+        | Commands::Source { .. } => true,
+        _ => false,
+    }
+}
+
+/// 按设备 ID 或名称查找设备，返回设备 ID。
+async fn find_device(xiaoai: &Xiaoai, target: &str) -> Result<String> {
+    let devices = xiaoai
+        .device_info_cached(DEVICE_CACHE_TTL)
+        .await
+        .context("获取设备列表失败")?;
+    if devices.iter().any(|device| device.device_id == target) {
+        return Ok(target.to_string());
+    }
+
+    Ok(xiaoai.device_by_name(target).await?.device_id)
+}
+
+/// 提示符中显示的设备名称，获取失败时显示设备 ID。
+async fn device_name(xiaoai: &Xiaoai, device_id: &str) -> String {
+    xiaoai
+        .device_info_cached(DEVICE_CACHE_TTL)
+        .await
+        .ok()
+        .and_then(|devices| devices.into_iter().find(|d| d.device_id == device_id))
+        .map_or_else(|| device_id.to_string(), |device| device.name)
+}
+
+/// 按空白拆分参数，支持用单引号或双引号包含空白。
+///
+/// 反斜杠转义下一个字符，单引号内除外。
+fn split_args(input: &str) -> Result<Vec<String>, &'static str> {
+    let mut args = Vec::new();
+    let mut current = None::<String>;
+    let mut quote = None;
+
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some('\'') => current.get_or_insert_default().push(c),
+            _ if c == '\\' => {
+                let escaped = chars.next().ok_or("末尾的 \\ 没有可以转义的字符")?;
+                current.get_or_insert_default().push(escaped);
+            }
+            Some(_) => current.get_or_insert_default().push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                current.get_or_insert_default();
+            }
+            None if c.is_whitespace() => args.extend(current.take()),
+            None => current.get_or_insert_default().push(c),
+        }
+    }
+    if quote.is_some() {
+        return Err("引号没有闭合");
+    }
+    args.extend(current);
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn supported_line(args: &[&str]) -> bool {
+        supported(&Line::try_parse_from(args).unwrap().command)
+    }
+
+    #[test]
+    fn split_quoted_args() {
+        assert_eq!(split_args("  say   你好 ").unwrap(), ["say", "你好"]);
+        assert_eq!(split_args(r#"say "你好 世界" 'a b'"#).unwrap(), ["say", "你好 世界", "a b"]);
+        assert_eq!(split_args(r#"say a"b c"d"#).unwrap(), ["say", "ab cd"]);
+        assert_eq!(split_args(r#"say """#).unwrap(), ["say", ""]);
+        assert!(split_args("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn split_escaped_args() {
+        assert_eq!(split_args(r"say 你好\ 世界").unwrap(), ["say", "你好 世界"]);
+        assert_eq!(split_args(r#"say \"a\" "b\"c""#).unwrap(), ["say", r#""a""#, r#"b"c"#]);
+        assert_eq!(split_args(r"say 'a\b' \\").unwrap(), ["say", r"a\b", r"\"]);
+    }
+
+    #[test]
+    fn split_unterminated_args() {
+        assert!(split_args(r#"say "你好"#).is_err());
+        assert!(split_args("say '你好").is_err());
+        assert!(split_args(r"say 你好\").is_err());
+    }
+
+    #[test]
+    fn only_device_commands_are_supported() {
+        assert!(supported_line(&["volume", "30"]));
+        assert!(supported_line(&["sleep", "30m"]));
+        assert!(!supported_line(&["sleep", "30m", "--local"]));
+        assert!(!supported_line(&["check"]));
+        assert!(!supported_line(&["diagnose"]));
+        assert!(!supported_line(&["say", "-"]));
+        assert!(!supported_line(&["login"]));
+    }
+}
@@ -0,0 +1,215 @@
+//! `run` 命令：复用同一个登录状态，按顺序执行 JSON 脚本中的命令。
+//!
+//! 脚本是一个数组，每一步的格式和 WebSocket API 的请求相同（参见 `API.md`），可以额外带上
+//! `sleep_ms`，执行完这一步后等待相应的毫秒数；只有 `sleep_ms` 的一步只等待。
+//! 没有 `device_id` 和 `device_name` 的步骤使用命令行指定的设备。
+//! `subscribe`、`unsubscribe` 和 `auth` 只对 WebSocket 连接有意义，不能用在脚本中。
+//!
+//! 执行前会先把每一步解析为 [`ApiRequest`]，任何一步格式有误都不会执行。
+//!
+//! ```json
+//! [
+//!   {"command": "volume", "volume": 30},
+//!   {"command": "ask", "text": "今天天气怎么样", "sleep_ms": 8000},
+//!   {"command": "play", "url": "http://example.com/radio.mp3"}
+//! ]
+//! ```
+
+use std::{path::Path, time::Instant};
+
+use anyhow::{Context, Result, anyhow, bail, ensure};
+use miai::{
+    Xiaoai,
+    wsapi::{ApiRequest, ApiResponse},
+};
+use serde_json::{Map, Value};
+
+use crate::{
+    Cli,
+    ws_server::{handle_request, request_from_value},
+};
+
+/// 不需要设备的命令，这些步骤不会补上命令行指定的设备
+const DEVICELESS_COMMANDS: &[&str] = &["get_devices", "describe"];
+
+/// 只对 WebSocket 连接有意义的命令，脚本中不支持
+const CONNECTION_COMMANDS: &[&str] = &["subscribe", "unsubscribe", "auth"];
+
+/// 脚本中的一步
+#[derive(Debug)]
+struct Step<T> {
+    /// 命令名，只等待时为空
+    command: String,
+    /// 去掉 `sleep_ms` 后的请求，只等待时为 `None`
+    request: Option<T>,
+    sleep_ms: Option<u64>,
+}
+
+pub async fn run(xiaoai: &Xiaoai, cli: &Cli, path: &Path, continue_on_error: bool) -> Result<()> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("无法读取脚本 {}", path.display()))?;
+    let steps = parse_steps(&text).with_context(|| format!("无效的脚本 {}", path.display()))?;
+    let steps = resolve_steps(steps, xiaoai, cli)
+        .await
+        .with_context(|| format!("无效的脚本 {}", path.display()))?;
+
+    let total = steps.len();
+    let mut failed = 0;
+    for (i, step) in steps.into_iter().enumerate() {
+        let progress = format!("[{}/{}]", i + 1, total);
+
+        if let Some(request) = step.request {
+            eprintln!("{} {}", progress, step.command);
+
+            let started = Instant::now();
+            let mut response = handle_request(request, xiaoai).await;
+            if let ApiResponse::Success { elapsed_ms, .. } = &mut response {
+                *elapsed_ms = Some(started.elapsed().as_millis() as u64);
+            }
+            println!("{}", serde_json::to_string(&response)?);
+
+            if matches!(response, ApiResponse::Error { .. } | ApiResponse::Unauthorized { .. }) {
+                failed += 1;
+                if !continue_on_error {
+                    bail!("第 {} 步 {} 执行失败，已停止", i + 1, step.command);
+                }
+            }
+        }
+
+        if let Some(sleep_ms) = step.sleep_ms {
+            eprintln!("{} 等待 {} 毫秒", progress, sleep_ms);
+            tokio::time::sleep(std::time::Duration::from_millis(sleep_ms)).await;
+        }
+    }
+
+    ensure!(failed == 0, "{} 个步骤执行失败", failed);
+    Ok(())
+}
+
+/// 检查整个脚本的 JSON 格式，拆出每一步的 `sleep_ms`。
+fn parse_steps(text: &str) -> Result<Vec<Step<Map<String, Value>>>> {
+    let steps: Vec<Value> = serde_json::from_str(text).context("脚本应为 JSON 数组")?;
+
+    steps
+        .into_iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let Value::Object(mut request) = step else {
+                bail!("第 {} 步应为 JSON 对象", i + 1);
+            };
+            let sleep_ms = match request.remove("sleep_ms") {
+                None => None,
+                Some(value) => Some(
+                    value
+                        .as_u64()
+                        .with_context(|| format!("第 {} 步的 sleep_ms 应为非负整数", i + 1))?,
+                ),
+            };
+            let command = match request.get("command") {
+                Some(Value::String(command)) => command.clone(),
+                Some(_) => bail!("第 {} 步的 command 应为字符串", i + 1),
+                None if request.is_empty() => String::new(),
+                None => bail!("第 {} 步缺少 command", i + 1),
+            };
+            let request = (!command.is_empty()).then_some(request);
+            ensure!(
+                request.is_some() || sleep_ms.is_some(),
+                "第 {} 步是空对象",
+                i + 1
+            );
+
+            Ok(Step { command, request, sleep_ms })
+        })
+        .collect()
+}
+
+/// 补上命令行指定的设备，并把每一步解析为 [`ApiRequest`]，执行前发现所有格式错误。
+async fn resolve_steps(
+    steps: Vec<Step<Map<String, Value>>>,
+    xiaoai: &Xiaoai,
+    cli: &Cli,
+) -> Result<Vec<Step<ApiRequest>>> {
+    let mut device_id = None;
+    let mut resolved = Vec::with_capacity(steps.len());
+    for (i, step) in steps.into_iter().enumerate() {
+        let request = match step.request {
+            Some(mut request) => {
+                ensure!(
+                    !CONNECTION_COMMANDS.contains(&step.command.as_str()),
+                    "第 {} 步: 脚本中不支持 {} 命令",
+                    i + 1,
+                    step.command
+                );
+                let needs_device = !DEVICELESS_COMMANDS.contains(&step.command.as_str())
+                    && !request.contains_key("device_id")
+                    && !request.contains_key("device_name");
+                if needs_device {
+                    if device_id.is_none() {
+                        device_id = Some(cli.device_id(xiaoai).await?.into_owned());
+                    }
+                    request.insert("device_id".to_string(), device_id.clone().into());
+                }
+
+                let request = request_from_value(Value::Object(request), xiaoai)
+                    .await
+                    .map_err(|error| anyhow!("第 {} 步: {}", i + 1, error))?;
+                Some(request)
+            }
+            None => None,
+        };
+
+        resolved.push(Step {
+            command: step.command,
+            request,
+            sleep_ms: step.sleep_ms,
+        });
+    }
+
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_sleep_only_step() {
+        let steps = parse_steps(r#"[{"command": "pause"}, {"sleep_ms": 500}]"#).unwrap();
+
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0].command, "pause");
+        assert!(steps[0].request.is_some());
+        assert_eq!(steps[0].sleep_ms, None);
+        assert_eq!(steps[1].command, "");
+        assert!(steps[1].request.is_none());
+        assert_eq!(steps[1].sleep_ms, Some(500));
+    }
+
+    #[test]
+    fn parse_step_without_command() {
+        let error = parse_steps(r#"[{"command": "pause"}, {"volume": 30}]"#).unwrap_err();
+        assert_eq!(error.to_string(), "第 2 步缺少 command");
+
+        assert!(parse_steps("[{}]").is_err());
+    }
+
+    #[test]
+    fn parse_negative_sleep_ms() {
+        let error = parse_steps(r#"[{"command": "pause", "sleep_ms": -1}]"#).unwrap_err();
+        assert_eq!(error.to_string(), "第 1 步的 sleep_ms 应为非负整数");
+    }
+
+    #[tokio::test]
+    async fn reject_connection_commands() {
+        use clap::Parser;
+
+        let xiaoai = Xiaoai::from_json_str("[]").unwrap();
+        let cli = Cli::try_parse_from(["xiaoai", "run", "script.json"]).unwrap();
+        for command in CONNECTION_COMMANDS {
+            let text = format!(r#"[{{"command": "pause", "device_id": "1"}}, {{"command": "{}"}}]"#, command);
+            let steps = parse_steps(&text).unwrap();
+            let error = resolve_steps(steps, &xiaoai, &cli).await.err().unwrap();
+            assert_eq!(error.to_string(), format!("第 2 步: 脚本中不支持 {} 命令", command));
+        }
+    }
+}
@@ -0,0 +1,59 @@
+//! 终端表格输出。
+
+use unicode_width::UnicodeWidthStr;
+
+/// 打印带边框、按显示宽度对齐的表格，中文等宽字符也能对齐。
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.width()).collect();
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.width());
+        }
+    }
+
+    let border = widths
+        .iter()
+        .map(|width| "-".repeat(width + 2))
+        .collect::<Vec<_>>()
+        .join("+");
+    let border = format!("+{}+", border);
+    let line = |cells: &mut dyn Iterator<Item = &str>| {
+        let cells: Vec<_> = cells
+            .zip(&widths)
+            .map(|(cell, width)| format!(" {}{} ", cell, " ".repeat(width - cell.width())))
+            .collect();
+        format!("|{}|", cells.join("|"))
+    };
+
+    println!("{}", border);
+    println!("{}", line(&mut headers.iter().copied()));
+    println!("{}", border);
+    for row in rows {
+        println!("{}", line(&mut row.iter().map(String::as_str)));
+    }
+    println!("{}", border);
+}
+
+/// 把 JSON 展开为两列的键值行，嵌套对象的键用 `.` 连接，数组保持 JSON 文本。
+pub fn key_value_rows(value: &serde_json::Value) -> Vec<Vec<String>> {
+    fn flatten(prefix: &str, value: &serde_json::Value, rows: &mut Vec<Vec<String>>) {
+        match value {
+            serde_json::Value::Object(map) => {
+                for (key, value) in map {
+                    let key = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    flatten(&key, value, rows);
+                }
+            }
+            serde_json::Value::String(s) => rows.push(vec![prefix.to_string(), s.clone()]),
+            other => rows.push(vec![prefix.to_string(), other.to_string()]),
+        }
+    }
+
+    let mut rows = Vec::new();
+    flatten("", value, &mut rows);
+    rows
+}
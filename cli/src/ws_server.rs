@@ -1,128 +1,247 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use anyhow::{Context, Result};
 use futures_util::{SinkExt, StreamExt};
-use miai::{PlayState, Xiaoai};
-use serde::{Deserialize, Serialize};
-use tokio::net::{TcpListener, TcpStream};
-use tokio::sync::{Mutex, RwLock};
-use tokio_tungstenite::{accept_async, tungstenite::Message};
-
-type ClientSender = futures_util::stream::SplitSink<tokio_tungstenite::WebSocketStream<TcpStream>, Message>;
-type Clients = Arc<RwLock<Vec<Arc<Mutex<ClientSender>>>>>;
-
-/// WebSocket API 请求
-#[derive(Debug, Deserialize)]
-#[serde(tag = "command", rename_all = "snake_case")]
-enum ApiRequest {
-    Say {
-        device_id: String,
-        text: String,
-    },
-    Play {
-        device_id: String,
-        url: Option<String>,
+use miai::{
+    PlayState, Xiaoai,
+    wsapi::{ApiRequest, ApiResponse, DeviceData},
+};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        ServerConfig,
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
     },
-    Pause {
-        device_id: String,
-    },
-    Stop {
-        device_id: String,
+};
+use tokio::sync::{Notify, RwLock, mpsc, watch};
+use tokio_tungstenite::{
+    accept_hdr_async,
+    tungstenite::{
+        Message,
+        handshake::server::{ErrorResponse, Request, Response},
+        http::StatusCode,
     },
-    Volume {
-        device_id: String,
-        volume: u32,
-    },
-    Ask {
-        device_id: String,
-        text: String,
-    },
-    Status {
-        device_id: String,
-    },
-    GetDevices,
-}
+};
 
-/// WebSocket API 响应
-#[derive(Debug, Serialize)]
-#[serde(tag = "type", rename_all = "snake_case")]
-enum ApiResponse {
-    Success {
-        code: i64,
-        message: String,
-        data: serde_json::Value,
-    },
-    Error {
-        error: String,
-    },
-    Devices {
-        devices: Vec<DeviceData>,
-    },
-    KeywordMatch {
-        timestamp: i64,
-        query: String,
-        matched_keyword: String,
-        device_id: String,
-    },
-}
+type Clients = Arc<RwLock<Vec<Client>>>;
 
-#[derive(Debug, Serialize)]
-struct DeviceData {
-    device_id: String,
-    name: String,
-    hardware: String,
+/// 每个客户端发送队列的默认容量
+pub const DEFAULT_QUEUE_CAPACITY: usize = 64;
+
+/// 已连接的客户端
+///
+/// 发往客户端的消息先进入容量有限的队列，再由单独的任务写入连接。
+/// 广播时如果队列已满，说明客户端跟不上，服务器会断开该客户端，而不是继续堆积消息。
+#[derive(Clone)]
+struct Client {
+    sender: mpsc::Sender<Message>,
+    /// 通知连接任务断开该客户端
+    kick: Arc<Notify>,
 }
 
+/// 关键词监听出错后的重启间隔
+const WATCHER_RESTART_DELAY: Duration = Duration::from_secs(10);
+
+/// 服务器支持的命令，由 `describe` 命令返回
+const SUPPORTED_COMMANDS: &[&str] = &[
+    "say",
+    "play",
+    "pause",
+    "stop",
+    "volume",
+    "ask",
+    "status",
+    "get_devices",
+    "get_device_status",
+    "describe",
+    "subscribe",
+    "unsubscribe",
+    "auth",
+];
+
+/// 开启鉴权时，等待客户端发送鉴权消息的时间
+const AUTH_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// WebSocket 服务器
 #[derive(Clone)]
 pub struct WsServer {
     xiaoai: Arc<Xiaoai>,
-    port: u16,
+    addr: SocketAddr,
+    /// 关键词监听使用的配置文件
+    watcher_config: PathBuf,
+    /// 关键词监听的心跳间隔
+    heartbeat: Option<Duration>,
+    /// 每个客户端发送队列的容量
+    queue_capacity: usize,
+    /// 访问令牌，`None` 表示不鉴权
+    auth_token: Option<Arc<str>>,
+    /// 设置后使用 wss://
+    tls: Option<TlsAcceptor>,
     clients: Clients,
+    /// 通知所有关键词监听任务停止
+    shutdown: Arc<watch::Sender<bool>>,
 }
 
 impl WsServer {
-    pub fn new(xiaoai: Xiaoai, port: u16) -> Self {
+    pub fn new(xiaoai: Xiaoai, addr: SocketAddr, watcher_config: PathBuf) -> Self {
         Self {
             xiaoai: Arc::new(xiaoai),
-            port,
+            addr,
+            watcher_config,
+            heartbeat: None,
+            queue_capacity: DEFAULT_QUEUE_CAPACITY,
+            auth_token: None,
+            tls: None,
             clients: Arc::new(RwLock::new(Vec::new())),
+            shutdown: Arc::new(watch::channel(false).0),
         }
     }
 
+    /// 设置关键词监听的心跳间隔
+    pub fn with_heartbeat(mut self, heartbeat: Option<Duration>) -> Self {
+        self.heartbeat = heartbeat;
+        self
+    }
+
+    /// 设置每个客户端发送队列的容量
+    pub fn with_queue_capacity(mut self, capacity: usize) -> Self {
+        self.queue_capacity = capacity;
+        self
+    }
+
+    /// 设置访问令牌，`None` 表示不鉴权（默认）
+    ///
+    /// 开启后，客户端可以在握手时通过 `Authorization: Bearer <token>` 请求头或 `?token=<token>`
+    /// 携带令牌，也可以在连接后发送的第一条消息中用 `auth` 命令鉴权。鉴权通过前不会收到广播，
+    /// 鉴权失败的连接会被立即关闭。
+    pub fn with_auth_token(mut self, token: Option<String>) -> Self {
+        self.auth_token = token.map(Arc::from);
+        self
+    }
+
+    /// 使用 PEM 格式的证书链和私钥开启 TLS，开启后服务器只接受 wss:// 连接
+    pub fn with_tls(mut self, cert: &Path, key: &Path) -> Result<Self> {
+        let certs = CertificateDer::pem_file_iter(cert)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .with_context(|| format!("无法读取证书 {}", cert.display()))?;
+        anyhow::ensure!(!certs.is_empty(), "证书文件 {} 中没有证书", cert.display());
+        let key = PrivateKeyDer::from_pem_file(key)
+            .with_context(|| format!("无法读取私钥 {}", key.display()))?;
+        let config = ServerConfig::builder_with_provider(Arc::new(
+            tokio_rustls::rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .context("初始化 TLS 失败")?
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .context("证书和私钥不匹配或格式不受支持")?;
+
+        self.tls = Some(TlsAcceptor::from(Arc::new(config)));
+        Ok(self)
+    }
+
     pub async fn run_server(&self) -> Result<()> {
-        let addr = SocketAddr::from(([0, 0, 0, 0], self.port));
-        let listener = TcpListener::bind(&addr).await?;
+        let addr = self.addr;
+        let listener = TcpListener::bind(&addr)
+            .await
+            .with_context(|| format!("无法监听 {}", addr))?;
         
         eprintln!("🚀 WebSocket 服务器已启动");
-        eprintln!("监听地址: ws://{}", addr);
+        let scheme = if self.tls.is_some() { "wss" } else { "ws" };
+        eprintln!("监听地址: {}://{}", scheme, addr);
         eprintln!("按 Ctrl+C 停止服务\n");
 
         loop {
             let (stream, peer_addr) = listener.accept().await?;
             let xiaoai = Arc::clone(&self.xiaoai);
             let clients = Arc::clone(&self.clients);
+            let queue_capacity = self.queue_capacity;
+            let auth_token = self.auth_token.clone();
+            let tls = self.tls.clone();
             
             tokio::spawn(async move {
-                if let Err(e) = handle_connection(stream, peer_addr, xiaoai, clients).await {
-                    eprintln!("处理连接 {} 时出错: {}", peer_addr, e);
+                let result = match tls {
+                    Some(tls) => match tls.accept(stream).await {
+                        Ok(stream) => {
+                            handle_connection(stream, peer_addr, xiaoai, clients, queue_capacity, auth_token).await
+                        }
+                        Err(e) => Err(anyhow::Error::new(e).context("TLS 握手失败")),
+                    },
+                    None => handle_connection(stream, peer_addr, xiaoai, clients, queue_capacity, auth_token).await,
+                };
+                if let Err(e) = result {
+                    eprintln!("处理连接 {} 时出错: {:#}", peer_addr, e);
                 }
             });
         }
     }
 
     /// 运行关键词监听器
-    pub async fn run_watcher(&self, device_id: String, hardware: String) -> Result<()> {
-        self.start_keyword_watcher(device_id, hardware).await
+    ///
+    /// 每台设备（`(device_id, hardware)`）各自运行一个监听任务，命中结果都会广播给所有客户端。
+    /// 单台设备的监听出错后会在一段时间后重启，不影响其他设备。
+    /// 调用 [`WsServer::shutdown`] 后，所有监听任务处理完当前的轮询后退出，此方法随之返回。
+    pub async fn run_watcher(&self, devices: Vec<(String, String)>) -> Result<()> {
+        let mut tasks = tokio::task::JoinSet::new();
+        for (device_id, hardware) in devices {
+            let server = self.clone();
+            tasks.spawn(async move { server.watch_device(device_id, hardware).await });
+        }
+        while tasks.join_next().await.is_some() {}
+
+        Ok(())
+    }
+
+    /// 停止所有关键词监听任务
+    ///
+    /// 监听任务不会被中途打断，正在执行的回调会先完成。
+    pub fn shutdown(&self) {
+        self.shutdown.send_replace(true);
+    }
+
+    /// 持续监听单台设备，出错后自动重启，收到停止信号后退出
+    async fn watch_device(&self, device_id: String, hardware: String) {
+        let mut shutdown = self.shutdown.subscribe();
+        while !*shutdown.borrow() {
+            if let Err(e) = self
+                .start_keyword_watcher(device_id.clone(), hardware.clone(), shutdown.clone())
+                .await
+            {
+                eprintln!(
+                    "设备 {} 的关键词监听出错: {:#}，{} 秒后重启",
+                    device_id,
+                    e,
+                    WATCHER_RESTART_DELAY.as_secs()
+                );
+            } else {
+                break;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(WATCHER_RESTART_DELAY) => {}
+                _ = shutdown.changed() => {}
+            }
+        }
     }
 
     /// 启动关键词监听（内部方法）
-    async fn start_keyword_watcher(&self, device_id: String, hardware: String) -> Result<()> {
+    async fn start_keyword_watcher(
+        &self,
+        device_id: String,
+        hardware: String,
+        shutdown: watch::Receiver<bool>,
+    ) -> Result<()> {
         use miai::ConversationWatcher;
         
-        let config_path = std::path::PathBuf::from("config.json");
-        let mut watcher = ConversationWatcher::from_json_file(&config_path)
-            .context("加载配置文件失败")?;
+        let mut watcher = ConversationWatcher::from_json_file(&self.watcher_config)?;
+        watcher.set_heartbeat(self.heartbeat);
         
         let clients = Arc::clone(&self.clients);
         let xiaoai = Arc::clone(&self.xiaoai);
@@ -144,8 +263,8 @@ impl WsServer {
         
         let device_id_clone = device_id.clone();
         
-        watcher
-            .watch(&xiaoai, &device_id, &hardware, move |keyword_match| {
+        let stats = watcher
+            .watch_until(&xiaoai, &device_id, &hardware, shutdown, move |keyword_match| {
                 let device_id = device_id_clone.clone();
                 let clients = Arc::clone(&clients);
                 
@@ -155,6 +274,7 @@ impl WsServer {
                         query: keyword_match.conversation.query.clone(),
                         matched_keyword: keyword_match.matched_keyword.to_string(),
                         device_id,
+                        captures: keyword_match.captures,
                     };
                     
                     match serde_json::to_string(&response) {
@@ -170,106 +290,314 @@ impl WsServer {
                 }
             })
             .await?;
-        
+
+        eprintln!(
+            "设备 {} 的关键词监听已停止，共轮询 {} 次，命中 {} 次，跳过重复 {} 次",
+            device_id, stats.polls, stats.matches, stats.duplicates
+        );
+
         Ok(())
     }
 }
 
 /// 向所有连接的客户端广播消息
+///
+/// 消息只会放入各客户端的发送队列，不会等待实际发送。队列已满的客户端会被断开，
+/// 已断开的客户端会被移出列表，因此慢客户端既不会拖慢其他客户端，也不会让内存无限增长。
 async fn broadcast_message(clients: &Clients, message: String) {
-    let clients_lock = clients.read().await;
-    let mut disconnected = Vec::new();
-    
-    for (idx, client) in clients_lock.iter().enumerate() {
-        let mut sender = client.lock().await;
-        if let Err(e) = sender.send(Message::Text(message.clone())).await {
-            eprintln!("发送消息到客户端 {} 失败: {}", idx, e);
-            disconnected.push(idx);
+    let mut failed = Vec::new();
+    for (idx, client) in clients.read().await.iter().enumerate() {
+        match client.sender.try_send(Message::Text(message.clone())) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                eprintln!("客户端 {} 的发送队列已满，断开连接", idx);
+                client.kick.notify_one();
+                failed.push(client.sender.clone());
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => {
+                failed.push(client.sender.clone());
+            }
         }
     }
-    
-    drop(clients_lock);
-    
-    // 清理断开连接的客户端
-    if !disconnected.is_empty() {
+
+    // 清理断开或跟不上的客户端
+    if !failed.is_empty() {
         let mut clients_lock = clients.write().await;
-        for idx in disconnected.iter().rev() {
-            clients_lock.remove(*idx);
-            eprintln!("移除断开的客户端 {}", idx);
-        }
+        clients_lock.retain(|client| !failed.iter().any(|f| f.same_channel(&client.sender)));
+        eprintln!("移除 {} 个断开或跟不上的客户端", failed.len());
     }
 }
 
-async fn handle_connection(
-    stream: TcpStream,
+async fn handle_connection<S>(
+    stream: S,
     peer_addr: SocketAddr,
     xiaoai: Arc<Xiaoai>,
     clients: Clients,
-) -> Result<()> {
+    queue_capacity: usize,
+    auth_token: Option<Arc<str>>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
     eprintln!("✅ 新连接: {}", peer_addr);
     
-    let ws_stream = accept_async(stream)
+    // 握手时携带了令牌就直接校验，令牌错误时拒绝握手；没有携带则等待第一条消息鉴权
+    let mut authorized = auth_token.is_none();
+    // 回调的签名由 tungstenite 决定，无法缩小错误类型
+    #[allow(clippy::result_large_err)]
+    let check_token = |request: &Request, response: Response| {
+        let Some(expected) = &auth_token else {
+            return Ok(response);
+        };
+        match handshake_token(request) {
+            Some(token) if token == **expected => {
+                authorized = true;
+                Ok(response)
+            }
+            Some(_) => {
+                let mut error = ErrorResponse::new(Some("invalid token".to_string()));
+                *error.status_mut() = StatusCode::UNAUTHORIZED;
+                Err(error)
+            }
+            None => Ok(response),
+        }
+    };
+    let ws_stream = accept_hdr_async(stream, check_token)
         .await
         .context("WebSocket 握手失败")?;
     
-    let (ws_sender, mut ws_receiver) = ws_stream.split();
+    let (mut ws_sender, mut ws_receiver) = ws_stream.split();
+
+    if let Some(expected) = auth_token.as_deref().filter(|_| !authorized) {
+        let message = tokio::time::timeout(AUTH_TIMEOUT, ws_receiver.next()).await;
+        let request = match message {
+            Ok(Some(Ok(Message::Text(text)))) => ApiRequest::try_from(text.as_str()).ok(),
+            _ => None,
+        };
+        let response = match request {
+            Some(ApiRequest::Auth { token }) if token == expected => ApiResponse::Success {
+                code: 0,
+                message: "OK".to_string(),
+                data: serde_json::Value::Null,
+                elapsed_ms: None,
+            },
+            _ => ApiResponse::Unauthorized {
+                error: "需要先发送正确的 auth 命令".to_string(),
+            },
+        };
+        let rejected = matches!(response, ApiResponse::Unauthorized { .. });
+        ws_sender
+            .send(Message::Text(serde_json::to_string(&response)?))
+            .await?;
+        if rejected {
+            eprintln!("🔒 连接 {} 鉴权失败，已关闭", peer_addr);
+            let _ = ws_sender.close().await;
+            return Ok(());
+        }
+    }
     
-    let ws_sender = Arc::new(Mutex::new(ws_sender));
+    // 由单独的任务把发送队列中的消息写入连接
+    let (sender, mut queue) = mpsc::channel(queue_capacity);
+    let writer = tokio::spawn(async move {
+        while let Some(message) = queue.recv().await {
+            if let Err(e) = ws_sender.send(message).await {
+                eprintln!("发送消息到 {} 失败: {}", peer_addr, e);
+                break;
+            }
+        }
+        let _ = ws_sender.close().await;
+    });
+    let client = Client {
+        sender: sender.clone(),
+        kick: Arc::new(Notify::new()),
+    };
+    // 此连接的状态订阅，设备 ID 到推送任务
+    let mut subscriptions = HashMap::new();
     
     // 将新客户端添加到客户端列表
     {
         let mut clients_lock = clients.write().await;
-        clients_lock.push(Arc::clone(&ws_sender));
+        clients_lock.push(client.clone());
         eprintln!("当前连接数: {}", clients_lock.len());
     }
     
-    while let Some(msg) = ws_receiver.next().await {
-        let msg = msg?;
-        
-        if msg.is_close() {
-            eprintln!("❌ 连接关闭: {}", peer_addr);
-            break;
-        }
-        
-        if !msg.is_text() {
-            continue;
-        }
-        
-        let text = msg.to_text()?;
-        eprintln!("📨 收到消息: {}", text);
-        
-        let response = match serde_json::from_str::<ApiRequest>(text) {
-            Ok(request) => {
-                let ws_sender_clone = Arc::clone(&ws_sender);
-                handle_request(request, &xiaoai, ws_sender_clone).await
+    let result = async {
+        loop {
+            let msg = tokio::select! {
+                msg = ws_receiver.next() => msg,
+                _ = client.kick.notified() => {
+                    eprintln!("❌ 客户端 {} 跟不上消息，已断开", peer_addr);
+                    writer.abort();
+                    break;
+                }
+            };
+            let Some(msg) = msg else { break };
+            let msg = msg?;
+            
+            if msg.is_close() {
+                eprintln!("❌ 连接关闭: {}", peer_addr);
+                break;
             }
-            Err(e) => ApiResponse::Error {
-                error: format!("无效的请求格式: {}", e),
-            },
-        };
-        
-        let response_text = serde_json::to_string(&response)?;
-        eprintln!("📤 发送响应: {}", response_text);
-        
-        let mut sender = ws_sender.lock().await;
-        sender.send(Message::Text(response_text)).await?;
+            
+            if !msg.is_text() {
+                continue;
+            }
+            
+            let text = msg.to_text()?;
+            eprintln!("📨 收到消息: {}", text);
+            
+            let response = match parse_request(text, &xiaoai).await {
+                Ok(ApiRequest::Subscribe { device_id, interval_secs }) => {
+                    subscribe(&mut subscriptions, &xiaoai, &sender, device_id, interval_secs)
+                }
+                Ok(ApiRequest::Unsubscribe { device_id }) => {
+                    match subscriptions.remove(&device_id) {
+                        Some(task) => {
+                            task.abort();
+                            ok_response()
+                        }
+                        None => ApiResponse::Error {
+                            error: format!("没有订阅设备 {}", device_id),
+                        },
+                    }
+                }
+                Ok(request) => {
+                    let started = Instant::now();
+                    let mut response = handle_request(request, &xiaoai).await;
+                    if let ApiResponse::Success { elapsed_ms, .. } = &mut response {
+                        *elapsed_ms = Some(started.elapsed().as_millis() as u64);
+                    }
+                    response
+                }
+                Err(error) => ApiResponse::Error { error },
+            };
+            
+            let response_text = serde_json::to_string(&response)?;
+            eprintln!("📤 发送响应: {}", response_text);
+            
+            if sender.send(Message::Text(response_text)).await.is_err() {
+                break;
+            }
+        }
+        anyhow::Ok(())
+    }
+    .await;
+
+    for task in subscriptions.into_values() {
+        task.abort();
     }
     
     // 从客户端列表中移除
     {
         let mut clients_lock = clients.write().await;
-        clients_lock.retain(|client| !Arc::ptr_eq(client, &ws_sender));
+        clients_lock.retain(|c| !c.sender.same_channel(&sender));
         eprintln!("当前连接数: {}", clients_lock.len());
     }
     
-    Ok(())
+    result
 }
 
-async fn handle_request(
-    request: ApiRequest,
-    xiaoai: &Xiaoai,
-    _ws_sender: Arc<Mutex<futures_util::stream::SplitSink<tokio_tungstenite::WebSocketStream<TcpStream>, Message>>>,
+/// 订阅设备的播放器状态，已经订阅的设备会用新的间隔重新开始推送
+fn subscribe(
+    subscriptions: &mut HashMap<String, tokio::task::JoinHandle<()>>,
+    xiaoai: &Arc<Xiaoai>,
+    sender: &mpsc::Sender<Message>,
+    device_id: String,
+    interval_secs: u64,
 ) -> ApiResponse {
+    if interval_secs == 0 {
+        return ApiResponse::Error {
+            error: "interval_secs 不能为 0".to_string(),
+        };
+    }
+
+    let xiaoai = Arc::clone(xiaoai);
+    let sender = sender.clone();
+    let id = device_id.clone();
+    let task = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(interval_secs));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let status = match xiaoai.player_status_parsed(&id).await {
+                Ok(status) => status,
+                Err(e) => {
+                    eprintln!("获取设备 {} 的状态失败: {}", id, e);
+                    continue;
+                }
+            };
+            let response = ApiResponse::StatusUpdate {
+                device_id: id.clone(),
+                status: status.raw,
+            };
+            let Ok(text) = serde_json::to_string(&response) else {
+                continue;
+            };
+            if sender.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    });
+    if let Some(previous) = subscriptions.insert(device_id, task) {
+        previous.abort();
+    }
+
+    ok_response()
+}
+
+fn ok_response() -> ApiResponse {
+    ApiResponse::Success {
+        code: 0,
+        message: "OK".to_string(),
+        data: serde_json::Value::Null,
+        elapsed_ms: None,
+    }
+}
+
+/// 握手请求中携带的令牌，来自 `Authorization: Bearer` 请求头或 `token` 查询参数。
+fn handshake_token(request: &Request) -> Option<String> {
+    let header = request
+        .headers()
+        .get("authorization")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+
+    header.or_else(|| {
+        url::form_urlencoded::parse(request.uri().query()?.as_bytes())
+            .find(|(key, _)| key == "token")
+            .map(|(_, value)| value.into_owned())
+    })
+}
+
+/// 解析请求，请求中只有 `device_name` 时，按名称查找设备并补上 `device_id`。
+async fn parse_request(text: &str, xiaoai: &Xiaoai) -> std::result::Result<ApiRequest, String> {
+    let value = serde_json::from_str(text).map_err(|e| format!("无效的请求格式: {}", e))?;
+    request_from_value(value, xiaoai).await
+}
+
+/// 同 [`parse_request`]，但从已经解析的 JSON 构造请求。
+pub(crate) async fn request_from_value(
+    mut value: serde_json::Value,
+    xiaoai: &Xiaoai,
+) -> std::result::Result<ApiRequest, String> {
+    if let Some(object) = value.as_object_mut() {
+        if !object.contains_key("device_id") {
+            if let Some(name) = object.remove("device_name") {
+                let name = name.as_str().ok_or("device_name 应为字符串")?;
+                let device = xiaoai
+                    .device_by_name(name)
+                    .await
+                    .map_err(|e| e.to_string())?;
+                object.insert("device_id".to_string(), device.device_id.into());
+            }
+        }
+    }
+
+    serde_json::from_value(value).map_err(|e| format!("无效的请求格式: {}", e))
+}
+
+pub(crate) async fn handle_request(request: ApiRequest, xiaoai: &Xiaoai) -> ApiResponse {
     let result = match request {
         ApiRequest::Say { device_id, text } => {
             xiaoai.tts(&device_id, &text).await
@@ -300,6 +628,7 @@ async fn handle_request(
                         code: 0,
                         message: "OK".to_string(),
                         data: status.raw,
+                        elapsed_ms: None,
                     };
                 }
                 Err(e) => {
@@ -332,6 +661,57 @@ async fn handle_request(
                 }
             }
         }
+        ApiRequest::GetDeviceStatus { device_id } => {
+            match xiaoai.device_status(&device_id).await {
+                Ok(status) => {
+                    let play_state = status.play_state.map(|state| match state {
+                        PlayState::Play => "play".to_string(),
+                        PlayState::Pause => "pause".to_string(),
+                        PlayState::Stop => "stop".to_string(),
+                        other => format!("{:?}", other).to_lowercase(),
+                    });
+                    return ApiResponse::Success {
+                        code: 0,
+                        message: "OK".to_string(),
+                        data: serde_json::json!({
+                            "device_id": status.info.device_id,
+                            "name": status.info.name,
+                            "hardware": status.info.hardware,
+                            "online": status.online,
+                            "firmware": status.firmware,
+                            "volume": status.volume,
+                            "play_state": play_state,
+                        }),
+                        elapsed_ms: None,
+                    };
+                }
+                Err(e) => {
+                    return ApiResponse::Error {
+                        error: format!("获取设备状态失败: {}", e),
+                    };
+                }
+            }
+        }
+        ApiRequest::Describe => {
+            return ApiResponse::Capabilities {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                commands: SUPPORTED_COMMANDS.iter().map(|c| c.to_string()).collect(),
+            };
+        }
+        // 已经通过鉴权的连接再次鉴权时直接返回成功
+        ApiRequest::Auth { .. } => {
+            return ApiResponse::Success {
+                code: 0,
+                message: "OK".to_string(),
+                data: serde_json::Value::Null,
+                elapsed_ms: None,
+            };
+        }
+        _ => {
+            return ApiResponse::Error {
+                error: "不支持的命令".to_string(),
+            };
+        }
     };
     
     match result {
@@ -339,9 +719,101 @@ async fn handle_request(
             code: response.code,
             message: response.message,
             data: response.data,
+            elapsed_ms: None,
         },
         Err(e) => ApiResponse::Error {
             error: format!("{}", e),
         },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(capacity: usize) -> (Client, mpsc::Receiver<Message>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        let client = Client {
+            sender,
+            kick: Arc::new(Notify::new()),
+        };
+        (client, receiver)
+    }
+
+    #[tokio::test]
+    async fn broadcast_skips_stuck_client() {
+        let (live, mut live_rx) = client(4);
+        // 队列已满且没有人读取，模拟跟不上的客户端
+        let (stuck, _stuck_rx) = client(1);
+        stuck.sender.try_send(Message::Text("旧消息".into())).unwrap();
+        let stuck_kick = Arc::clone(&stuck.kick);
+        let clients: Clients = Arc::new(RwLock::new(vec![stuck, live.clone()]));
+
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            broadcast_message(&clients, "你好".to_string()),
+        )
+        .await
+        .expect("广播不应被卡住的客户端阻塞");
+
+        let received = tokio::time::timeout(Duration::from_secs(1), live_rx.recv())
+            .await
+            .expect("正常的客户端应立即收到消息");
+        assert_eq!(received, Some(Message::Text("你好".into())));
+
+        let clients = clients.read().await;
+        assert_eq!(clients.len(), 1);
+        assert!(clients[0].sender.same_channel(&live.sender));
+        // 被移除的客户端会收到断开通知
+        tokio::time::timeout(Duration::from_secs(1), stuck_kick.notified())
+            .await
+            .expect("跟不上的客户端应被通知断开");
+    }
+
+    #[tokio::test]
+    async fn broadcast_removes_closed_client() {
+        let (live, mut live_rx) = client(4);
+        let (closed, closed_rx) = client(4);
+        drop(closed_rx);
+        let clients: Clients = Arc::new(RwLock::new(vec![closed, live]));
+
+        broadcast_message(&clients, "你好".to_string()).await;
+
+        assert_eq!(live_rx.recv().await, Some(Message::Text("你好".into())));
+        assert_eq!(clients.read().await.len(), 1);
+    }
+
+    #[test]
+    fn supported_commands_match_requests() {
+        // 列出的每个命令都是 ApiRequest 的标签，缺少字段不算错误
+        for command in SUPPORTED_COMMANDS {
+            let request = serde_json::json!({ "command": command });
+            if let Err(e) = serde_json::from_value::<ApiRequest>(request) {
+                assert!(!e.to_string().contains("unknown variant"), "{}: {}", command, e);
+            }
+        }
+
+        // 每种请求的标签都在列表中
+        let requests = [
+            ApiRequest::say("id", "你好"),
+            ApiRequest::play("id", None),
+            ApiRequest::pause("id"),
+            ApiRequest::stop("id"),
+            ApiRequest::volume("id", 30),
+            ApiRequest::ask("id", "你好"),
+            ApiRequest::status("id"),
+            ApiRequest::GetDevices,
+            ApiRequest::device_status("id"),
+            ApiRequest::Describe,
+            ApiRequest::subscribe("id", 5),
+            ApiRequest::unsubscribe("id"),
+            ApiRequest::auth("token"),
+        ];
+        assert_eq!(requests.len(), SUPPORTED_COMMANDS.len());
+        for request in requests {
+            let value = serde_json::to_value(&request).unwrap();
+            let command = value["command"].as_str().unwrap();
+            assert!(SUPPORTED_COMMANDS.contains(&command), "describe 缺少 {}", command);
+        }
+    }
+}
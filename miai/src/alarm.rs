@@ -0,0 +1,111 @@
+//! 闹钟。
+//!
+//! 小米没有公开管理闹钟的接口，这里把闹钟描述转换为一条语音指令，再通过
+//! [`Xiaoai::nlp`][crate::Xiaoai::nlp] 交给小爱执行，效果和口头设置闹钟一样。
+//!
+//! 也因此闹钟没有 ID：[`Xiaoai::list_alarms`][crate::Xiaoai::list_alarms] 得到的是小爱的回答，
+//! 而不是结构化的列表；[`Xiaoai::delete_alarm`][crate::Xiaoai::delete_alarm] 按时间和重复规则删除。
+//!
+//! # 示例
+//!
+//! ```
+//! use miai::alarm::{AlarmRepeat, AlarmSpec};
+//!
+//! let spec = AlarmSpec::new(7, 30).unwrap().repeat(AlarmRepeat::Weekdays);
+//! assert_eq!(spec.command(), "设置每个工作日7点30分的闹钟");
+//! assert_eq!(spec.delete_command(), "删除每个工作日7点30分的闹钟");
+//!
+//! let spec = AlarmSpec::new(21, 0).unwrap().content("吃药");
+//! assert_eq!(spec.command(), "21点提醒我吃药");
+//!
+//! assert!(AlarmSpec::new(24, 0).is_err());
+//! ```
+
+use crate::time::Time;
+
+/// 查询闹钟的语音指令。
+pub const LIST_COMMAND: &str = "我有哪些闹钟";
+
+/// 删除所有闹钟的语音指令。
+pub const DELETE_ALL_COMMAND: &str = "删除所有闹钟";
+
+/// 闹钟的重复规则。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AlarmRepeat {
+    /// 只响一次，时间已过时由小爱顺延到第二天。
+    #[default]
+    Once,
+    /// 每天。
+    Daily,
+    /// 每个工作日。
+    Weekdays,
+    /// 每个周末。
+    Weekends,
+}
+
+impl AlarmRepeat {
+    fn prefix(self) -> &'static str {
+        match self {
+            AlarmRepeat::Once => "",
+            AlarmRepeat::Daily => "每天",
+            AlarmRepeat::Weekdays => "每个工作日",
+            AlarmRepeat::Weekends => "每个周末",
+        }
+    }
+}
+
+/// 一个闹钟的描述，用于 [`Xiaoai::create_alarm`][crate::Xiaoai::create_alarm]。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlarmSpec {
+    time: Time,
+    repeat: AlarmRepeat,
+    content: Option<String>,
+}
+
+impl AlarmSpec {
+    /// 在 `hour` 点 `minute` 分响铃的闹钟，使用 24 小时制。
+    ///
+    /// # Errors
+    ///
+    /// 时间无效时，将返回 [`Error::InvalidTime`][crate::Error::InvalidTime]。
+    pub fn new(hour: u8, minute: u8) -> crate::Result<Self> {
+        Time::new(hour, minute).map(Self::at)
+    }
+
+    /// 在 `time` 响铃的闹钟。
+    pub fn at(time: Time) -> Self {
+        Self {
+            time,
+            repeat: AlarmRepeat::default(),
+            content: None,
+        }
+    }
+
+    /// 设置重复规则，默认只响一次。
+    pub fn repeat(mut self, repeat: AlarmRepeat) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// 设置响铃时播报的提醒内容，设置后会创建提醒而不是普通闹钟。
+    pub fn content(mut self, content: impl Into<String>) -> Self {
+        self.content = Some(content.into());
+        self
+    }
+
+    /// 交给小爱执行的语音指令。
+    pub fn command(&self) -> String {
+        let prefix = self.repeat.prefix();
+
+        match &self.content {
+            Some(content) => format!("{}{}提醒我{}", prefix, self.time, content),
+            None => format!("设置{}{}的闹钟", prefix, self.time),
+        }
+    }
+
+    /// 删除这个闹钟的语音指令，提醒内容不影响删除。
+    pub fn delete_command(&self) -> String {
+        format!("删除{}{}的闹钟", self.repeat.prefix(), self.time)
+    }
+}
@@ -0,0 +1,158 @@
+//! 抽象出常用操作的 [`XiaoaiApi`]，便于在测试中替换 [`Xiaoai`]。
+//!
+//! 依赖 `miai` 的代码可以接受 `&dyn XiaoaiApi` 或 `impl XiaoaiApi`，运行时传入 [`Xiaoai`]，
+//! 测试时传入开启 `mock` feature 后提供的 `mock::MockXiaoai`，无需发送网络请求。
+//!
+//! # 示例
+//!
+//! ```no_run
+//! use miai::{Xiaoai, api::XiaoaiApi};
+//!
+//! async fn greet_all(api: &dyn XiaoaiApi) -> miai::Result<()> {
+//!     for info in api.device_info().await? {
+//!         api.tts(&info.device_id, "你好！").await?;
+//!     }
+//!     Ok(())
+//! }
+//!
+//! # async fn example(xiaoai: Xiaoai) -> miai::Result<()> {
+//! greet_all(&xiaoai).await
+//! # }
+//! ```
+
+use std::{future::Future, pin::Pin};
+
+use crate::{DeviceInfo, PlayState, Xiaoai, XiaoaiResponse};
+
+/// [`XiaoaiApi`] 的方法返回的 future。
+///
+/// trait 中的 `async fn` 无法用于 `dyn XiaoaiApi`，因此这里返回装箱的 future。
+pub type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// 小爱服务的常用操作，方法的含义与 [`Xiaoai`] 上的同名方法相同。
+///
+/// 此 trait 是对象安全的，可以用作 `dyn XiaoaiApi`。
+pub trait XiaoaiApi: Send + Sync {
+    /// 见 [`Xiaoai::device_info`]。
+    fn device_info(&self) -> BoxFuture<'_, crate::Result<Vec<DeviceInfo>>>;
+
+    /// 见 [`Xiaoai::ubus_call`]。
+    fn ubus_call<'a>(
+        &'a self,
+        device_id: &'a str,
+        path: &'a str,
+        method: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>>;
+
+    /// 见 [`Xiaoai::tts`]。
+    fn tts<'a>(
+        &'a self,
+        device_id: &'a str,
+        text: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>>;
+
+    /// 见 [`Xiaoai::play_url`]。
+    fn play_url<'a>(
+        &'a self,
+        device_id: &'a str,
+        url: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>>;
+
+    /// 见 [`Xiaoai::set_volume`]。
+    fn set_volume<'a>(
+        &'a self,
+        device_id: &'a str,
+        volume: u32,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>>;
+
+    /// 见 [`Xiaoai::get_volume`]。
+    fn get_volume<'a>(&'a self, device_id: &'a str) -> BoxFuture<'a, crate::Result<u32>>;
+
+    /// 见 [`Xiaoai::set_play_state`]。
+    fn set_play_state<'a>(
+        &'a self,
+        device_id: &'a str,
+        state: PlayState,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>>;
+
+    /// 见 [`Xiaoai::nlp`]。
+    fn nlp<'a>(
+        &'a self,
+        device_id: &'a str,
+        text: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>>;
+
+    /// 见 [`Xiaoai::player_status`]。
+    fn player_status<'a>(
+        &'a self,
+        device_id: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>>;
+}
+
+impl XiaoaiApi for Xiaoai {
+    fn device_info(&self) -> BoxFuture<'_, crate::Result<Vec<DeviceInfo>>> {
+        Box::pin(Xiaoai::device_info(self))
+    }
+
+    fn ubus_call<'a>(
+        &'a self,
+        device_id: &'a str,
+        path: &'a str,
+        method: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        Box::pin(Xiaoai::ubus_call(self, device_id, path, method, message))
+    }
+
+    fn tts<'a>(
+        &'a self,
+        device_id: &'a str,
+        text: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        Box::pin(Xiaoai::tts(self, device_id, text))
+    }
+
+    fn play_url<'a>(
+        &'a self,
+        device_id: &'a str,
+        url: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        Box::pin(Xiaoai::play_url(self, device_id, url))
+    }
+
+    fn set_volume<'a>(
+        &'a self,
+        device_id: &'a str,
+        volume: u32,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        Box::pin(Xiaoai::set_volume(self, device_id, volume))
+    }
+
+    fn get_volume<'a>(&'a self, device_id: &'a str) -> BoxFuture<'a, crate::Result<u32>> {
+        Box::pin(Xiaoai::get_volume(self, device_id))
+    }
+
+    fn set_play_state<'a>(
+        &'a self,
+        device_id: &'a str,
+        state: PlayState,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        Box::pin(Xiaoai::set_play_state(self, device_id, state))
+    }
+
+    fn nlp<'a>(
+        &'a self,
+        device_id: &'a str,
+        text: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        Box::pin(Xiaoai::nlp(self, device_id, text))
+    }
+
+    fn player_status<'a>(
+        &'a self,
+        device_id: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        Box::pin(Xiaoai::player_status(self, device_id))
+    }
+}
@@ -0,0 +1,107 @@
+//! 构造登录和请求小爱服务所用的 [`Client`]。
+
+use std::{
+    sync::{Arc, LazyLock},
+    time::Duration,
+};
+
+use reqwest::{Client, ClientBuilder, Proxy, Url, redirect::Policy};
+use reqwest_cookie_store::CookieStoreMutex;
+
+/// 默认模拟的米家 APP 版本。
+pub const DEFAULT_APP_VERSION: &str = "6.0.103";
+
+/// 默认的小爱服务 API 服务器。
+const API_SERVER: &str = "https://api2.mina.mi.com/";
+static API_SERVER_URL: LazyLock<Url> =
+    LazyLock::new(|| Url::parse(API_SERVER).expect("API_SERVER 应为合法的 URL"));
+
+/// 登录时最多跟随的重定向次数。
+const MAX_REDIRECTS: usize = 10;
+
+/// 默认的请求超时时间。
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// [`Login`][crate::login::Login] 和 [`Xiaoai`][crate::Xiaoai] 共用的 `Client` 设置。
+///
+/// [`Client`] 构造后无法修改 UA 等设置，修改后需要重新构造；`api_server` 只在请求时使用，
+/// 放在这里是为了让它随 [`Xiaoai::from_login`][crate::Xiaoai::from_login] 一起传递。
+#[derive(Clone, Debug)]
+pub(crate) struct ClientConfig {
+    pub app_version: String,
+    pub accept_invalid_certs: bool,
+    pub api_server: Url,
+    pub timeout: Duration,
+    pub max_retries: u32,
+    /// 被限流时是否等待后重试一次
+    pub retry_on_rate_limit: bool,
+    /// 为 `None` 时使用 reqwest 的默认行为，即读取 `HTTP_PROXY`、`HTTPS_PROXY` 等环境变量
+    pub proxy: Option<Proxy>,
+    /// 登录时的 User-Agent，为 `None` 时按 `app_version` 生成
+    pub login_user_agent: Option<String>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            app_version: DEFAULT_APP_VERSION.to_string(),
+            accept_invalid_certs: false,
+            api_server: API_SERVER_URL.clone(),
+            timeout: DEFAULT_TIMEOUT,
+            max_retries: 0,
+            retry_on_rate_limit: false,
+            proxy: None,
+            login_user_agent: None,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// 用于登录的 Client。
+    ///
+    /// 获取 token 时会经过跨域重定向，每一跳都会读写同一个 cookie_store，
+    /// 这里显式限制重定向次数，避免依赖 reqwest 的默认策略。
+    pub fn login_client(&self, cookie_store: &Arc<CookieStoreMutex>) -> reqwest::Result<Client> {
+        self.builder()
+            .cookie_provider(Arc::clone(cookie_store))
+            .redirect(Policy::limited(MAX_REDIRECTS))
+            .user_agent(self.login_ua())
+            .build()
+    }
+
+    /// 用于请求小爱服务的 Client。
+    pub fn api_client(&self, cookie_store: &Arc<CookieStoreMutex>) -> reqwest::Result<Client> {
+        self.builder()
+            .cookie_provider(Arc::clone(cookie_store))
+            .user_agent(self.api_ua())
+            .build()
+    }
+
+    /// 两种 Client 共用的设置。
+    fn builder(&self) -> ClientBuilder {
+        let builder = Client::builder()
+            .timeout(self.timeout)
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        match &self.proxy {
+            Some(proxy) => builder.proxy(proxy.clone()),
+            None => builder,
+        }
+    }
+
+    fn login_ua(&self) -> String {
+        if let Some(user_agent) = &self.login_user_agent {
+            return user_agent.clone();
+        }
+        format!(
+            "APP/com.xiaomi.mihome APPV/{} iosPassportSDK/3.9.0 iOS/14.4 miHSTS",
+            self.app_version
+        )
+    }
+
+    fn api_ua(&self) -> String {
+        let version = &self.app_version;
+        format!(
+            "MiHome/{version} (com.xiaomi.mihome; build:{version}.1; iOS 14.4.0) Alamofire/{version} MICO/iOSApp/appStore/{version}"
+        )
+    }
+}
@@ -0,0 +1,79 @@
+//! 登录状态的加密存储。
+//!
+//! 密文格式为 `MAGIC | 版本(1 字节) | salt(16 字节) | nonce(12 字节) | 密文和 tag`。
+//! 由口令经 PBKDF2-HMAC-SHA256 派生出密钥，再使用 AES-256-GCM 加密原先的 json。
+
+use std::num::NonZeroU32;
+
+use ring::{
+    aead::{AES_256_GCM, Aad, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+
+/// 加密文件的开头，用于识别明文和密文。
+const MAGIC: &[u8] = b"MIAIENC";
+const VERSION: u8 = 1;
+const SALT_LEN: usize = 16;
+const PBKDF2_ITERATIONS: NonZeroU32 = NonZeroU32::new(600_000).unwrap();
+
+/// `bytes` 是否是加密后的登录状态。
+pub fn is_encrypted(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// 使用 `passphrase` 加密 `plain`。
+pub fn encrypt(plain: &[u8], passphrase: &str) -> crate::Result<Vec<u8>> {
+    let rng = SystemRandom::new();
+    let mut salt = [0; SALT_LEN];
+    let mut nonce = [0; NONCE_LEN];
+    rng.fill(&mut salt).map_err(|_| crate::Error::Crypto)?;
+    rng.fill(&mut nonce).map_err(|_| crate::Error::Crypto)?;
+
+    let mut in_out = plain.to_vec();
+    derive_key(passphrase, &salt)?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut in_out)
+        .map_err(|_| crate::Error::Crypto)?;
+
+    let mut output = Vec::with_capacity(MAGIC.len() + 1 + SALT_LEN + NONCE_LEN + in_out.len());
+    output.extend_from_slice(MAGIC);
+    output.push(VERSION);
+    output.extend_from_slice(&salt);
+    output.extend_from_slice(&nonce);
+    output.extend_from_slice(&in_out);
+
+    Ok(output)
+}
+
+/// 使用 `passphrase` 解密 [`encrypt`] 的结果。
+pub fn decrypt(data: &[u8], passphrase: &str) -> crate::Result<Vec<u8>> {
+    let data = data.strip_prefix(MAGIC).ok_or(crate::Error::Crypto)?;
+    let (&version, data) = data.split_first().ok_or(crate::Error::Crypto)?;
+    if version != VERSION || data.len() < SALT_LEN + NONCE_LEN {
+        return Err(crate::Error::Crypto);
+    }
+    let (salt, data) = data.split_at(SALT_LEN);
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| crate::Error::Crypto)?;
+
+    let mut in_out = ciphertext.to_vec();
+    let plain = derive_key(passphrase, salt)?
+        .open_in_place(nonce, Aad::empty(), &mut in_out)
+        .map_err(|_| crate::Error::Crypto)?;
+
+    Ok(plain.to_vec())
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> crate::Result<LessSafeKey> {
+    let mut key = [0; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        PBKDF2_ITERATIONS,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    let key = UnboundKey::new(&AES_256_GCM, &key).map_err(|_| crate::Error::Crypto)?;
+
+    Ok(LessSafeKey::new(key))
+}
@@ -0,0 +1,34 @@
+//! 勿扰模式。
+//!
+//! 和 [`alarm`][crate::alarm] 一样，小米没有公开勿扰模式的设置接口，这里把设置转换为一条语音指令，
+//! 再通过 [`Xiaoai::nlp`][crate::Xiaoai::nlp] 交给小爱执行。查询同样通过语音完成，
+//! [`Xiaoai::get_do_not_disturb`][crate::Xiaoai::get_do_not_disturb] 返回小爱的回答，
+//! 不依赖任何固件特定的返回格式。
+//!
+//! # 示例
+//!
+//! ```
+//! use miai::{dnd, time::Time};
+//!
+//! let start = Time::new(23, 0).unwrap();
+//! let end = Time::new(7, 30).unwrap();
+//! assert_eq!(dnd::command(true, Some(start), Some(end)), "设置23点到7点30分的勿扰模式");
+//! assert_eq!(dnd::command(true, None, None), "打开勿扰模式");
+//! assert_eq!(dnd::command(false, None, None), "关闭勿扰模式");
+//! ```
+
+use crate::time::Time;
+
+/// 查询勿扰模式状态的语音指令。
+pub const QUERY_COMMAND: &str = "勿扰模式开了吗";
+
+/// 交给小爱执行的语音指令。
+///
+/// 开启且同时给出 `start` 和 `end` 时设置勿扰时间段，否则只是打开或关闭勿扰模式。
+pub fn command(enabled: bool, start: Option<Time>, end: Option<Time>) -> String {
+    match (enabled, start, end) {
+        (true, Some(start), Some(end)) => format!("设置{}到{}的勿扰模式", start, end),
+        (true, _, _) => "打开勿扰模式".to_string(),
+        (false, _, _) => "关闭勿扰模式".to_string(),
+    }
+}
@@ -2,9 +2,11 @@ use crate::XiaoaiResponse;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
+/// `miai` 的错误类型。
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum Error {
-    #[error("API 返回 {}: {}", .0.code, .0.message)]
+    #[error("API 返回 {}: {}", .0.code, api_message(.0))]
     Api(XiaoaiResponse),
 
     #[error(transparent)]
@@ -13,9 +15,161 @@ pub enum Error {
     #[error(transparent)]
     Json(#[from] serde_json::Error),
 
+    #[error("无法解析响应数据: {source}，原始数据: {raw}")]
+    DataDecode {
+        #[source]
+        source: serde_json::Error,
+        /// 原始的 `data`，过长时会被截断
+        raw: String,
+    },
+
     #[error(transparent)]
     Cookie(#[from] cookie_store::CookieError),
 
+    #[error("读写登录状态失败: {0}")]
+    CookieStore(#[source] Box<dyn std::error::Error + Send + Sync>),
+
     #[error(transparent)]
     Url(#[from] url::ParseError),
+
+    #[error("登录流程结束，但没有获得 serviceToken")]
+    TokenNotObtained,
+
+    #[error("登录失败 ({code}): {reason}")]
+    LoginFailed { code: i64, reason: String },
+
+    #[error("需要完成验证后才能登录: {url}")]
+    NeedCaptcha { url: String },
+
+    #[error("需要完成二次验证后才能登录: {url}")]
+    NeedVerification { url: String },
+
+    #[error("缺少必需的 Cookie: {0}")]
+    MissingCookie(&'static str),
+
+    #[error("登录状态已失效，需要重新登录")]
+    SessionExpired,
+
+    #[error("请求过于频繁，已被限流{}", retry_after_hint(.retry_after))]
+    RateLimited {
+        /// 服务端建议的等待时间，取自 `Retry-After` 响应头，没有时为 `None`
+        retry_after: Option<std::time::Duration>,
+    },
+
+    #[error("登录状态已加密，需要提供口令")]
+    Encrypted,
+
+    #[error("解密登录状态失败，口令错误或文件已损坏")]
+    Crypto,
+
+    #[error("找不到设备 {0}")]
+    DeviceNotFound(String),
+
+    #[error("有多个名为 {0} 的设备，请改用设备 ID 指定")]
+    AmbiguousDevice(String),
+
+    #[error("{operation}失败，设备可能不支持该操作（API 返回 {}: {}）", .response.code, api_message(.response))]
+    OperationUnsupported {
+        operation: &'static str,
+        response: XiaoaiResponse,
+    },
+
+    #[error("音量 {} 超出范围 {}..={}", .0, crate::model::VOLUME_RANGE.start(), crate::model::VOLUME_RANGE.end())]
+    InvalidVolume(u32),
+
+    #[error("无效的时间 {hour}:{minute:02}")]
+    InvalidTime { hour: u8, minute: u8 },
+
+    #[error("未知的电台: {0}")]
+    UnknownStation(String),
+
+    #[error("加载配置文件 {} 失败", .path.display())]
+    ConfigFile {
+        path: std::path::PathBuf,
+        #[source]
+        source: Box<Error>,
+    },
+
+    #[error("无效的关键词正则表达式 {pattern}: {source}")]
+    InvalidRegex {
+        pattern: String,
+        #[source]
+        source: regex::Error,
+    },
+
+    #[error("无法解析对话记录: {0}")]
+    ConversationUnavailable(String),
+
+    #[error("无法解析播放器状态: {0}")]
+    StatusUnavailable(serde_json::Value),
+}
+
+/// [`Error::Api`] 展示的消息，优先附带 `data` 中更具体的描述。
+fn api_message(response: &XiaoaiResponse) -> String {
+    match response.description() {
+        Some(desc) if desc != response.message => format!("{}（{}）", response.message, desc),
+        _ => response.message.clone(),
+    }
+}
+
+/// [`Error::RateLimited`] 展示的等待时间。
+fn retry_after_hint(retry_after: &Option<std::time::Duration>) -> String {
+    match retry_after {
+        Some(retry_after) => format!("，请在 {} 秒后重试", retry_after.as_secs()),
+        None => String::new(),
+    }
+}
+
+/// 业务错误的分类，由 [`Error::api_error_kind`] 或 [`XiaoaiResponse::error_kind`] 得到。
+///
+/// 小米没有公开错误码，也没有可靠的社区码表，因此这里不按错误码分类，
+/// 非 0 的错误码都归为 [`Unknown`][ApiErrorKind::Unknown]。
+/// 登录失效和限流已经分别转换为 [`Error::SessionExpired`] 和 [`Error::RateLimited`]，不会出现在这里。
+///
+/// 设备离线时错误码并不固定，因此按 `message` 和 [`XiaoaiResponse::description`]
+/// 中是否含有“离线”“不在线”“offline”判断为 [`DeviceOffline`][ApiErrorKind::DeviceOffline]。
+///
+/// # Examples
+///
+/// ```
+/// # use miai::{ApiErrorKind, XiaoaiResponse};
+/// let res = XiaoaiResponse {
+///     code: 101,
+///     message: "error".to_string(),
+///     data: serde_json::json!({"info": {"description": "设备不在线"}}),
+/// };
+/// assert_eq!(res.error_kind(), ApiErrorKind::DeviceOffline);
+/// assert_eq!(ApiErrorKind::from_code(-7), ApiErrorKind::Unknown(-7));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ApiErrorKind {
+    /// 没有错误
+    Ok,
+    /// 设备不在线
+    DeviceOffline,
+    /// 其他错误
+    Unknown(i64),
+}
+
+impl ApiErrorKind {
+    /// 仅按错误码分类，不检查消息内容。
+    pub fn from_code(code: i64) -> Self {
+        match code {
+            0 => Self::Ok,
+            code => Self::Unknown(code),
+        }
+    }
+}
+
+impl Error {
+    /// [`Error::Api`] 的错误分类，其他错误返回 `None`。
+    pub fn api_error_kind(&self) -> Option<ApiErrorKind> {
+        match self {
+            Error::Api(response) | Error::OperationUnsupported { response, .. } => {
+                Some(response.error_kind())
+            }
+            _ => None,
+        }
+    }
 }
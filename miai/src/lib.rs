@@ -27,26 +27,66 @@
 //!     }
 //! }
 //! ```
+//!
+//! # 日志
+//!
+//! 库使用 [`tracing`](https://docs.rs/tracing) 输出日志。[`Xiaoai::get`]、[`Xiaoai::post`]
+//! 和 [`Xiaoai::ubus_call`] 会在 `debug` 级别创建 span，记录 requestId、uri、设备 ID、耗时和响应的 `code`，
+//! 接入 `tracing-subscriber` 后可以把一次操作内的多个请求关联起来。
+//!
+//! # 语音指令
+//!
+//! 小米没有公开闹钟、勿扰模式等设置的接口。这类操作只要小爱有等效的语音指令，
+//! 就转换为语音指令交给 [`Xiaoai::nlp`] 执行，查询则通过 [`Xiaoai::ask`] 返回小爱的回答，
+//! 见 [`alarm`]、[`dnd`]、[`sleep_timer`] 和 [`source`]。
+//!
+//! 童锁、按键音、唤醒提示音既没有已知的接口，也没有等效的语音指令，因此没有提供。
+//!
+//! # 测试
+//!
+//! 常用操作抽象为 [`api::XiaoaiApi`]，开启 `mock` feature 后可以用 `mock::MockXiaoai`
+//! 代替 [`Xiaoai`]，在单元测试中记录调用并返回预设的响应。
 
+pub mod alarm;
+pub mod api;
+mod client;
+mod crypto;
+pub mod dnd;
 mod error;
 pub mod login;
+#[cfg(feature = "mock")]
+pub mod mock;
+pub mod model;
+pub mod radio;
+pub mod sleep_timer;
+pub mod source;
+pub mod ssml;
+pub mod time;
+pub mod ubus;
 mod util;
 mod xiaoai;
 pub mod watcher;
+pub mod wsapi;
 
 use serde::{Deserialize, de::DeserializeOwned};
 use serde_json::Value;
 
+pub use client::{DEFAULT_APP_VERSION, DEFAULT_TIMEOUT};
+pub use reqwest::Proxy;
 pub use error::*;
 pub use xiaoai::*;
 pub use watcher::*;
 
+/// [`Error::DataDecode`] 中保留的原始数据的最大字符数。
+const MAX_RAW_DATA_CHARS: usize = 1000;
+
 /// 小爱服务请求的响应。
 #[derive(Clone, Deserialize, Debug)]
 pub struct XiaoaiResponse<T = Value> {
     /// 错误码。
     ///
     /// 非 0 的错误码表示当前请求出错了。
+    #[serde(deserialize_with = "util::deserialize_code")]
     pub code: i64,
 
     /// 一条简短的消息。
@@ -87,12 +127,104 @@ impl XiaoaiResponse {
         }
     }
 
+    /// 按 `code` 和错误消息对响应分类，详见 [`ApiErrorKind`]。
+    pub fn error_kind(&self) -> ApiErrorKind {
+        const OFFLINE: &[&str] = &["离线", "不在线", "offline"];
+
+        if self.code != 0 {
+            let offline = [Some(self.message.as_str()), self.description()]
+                .into_iter()
+                .flatten()
+                .any(|text| {
+                    let text = text.to_lowercase();
+                    OFFLINE.iter().any(|keyword| text.contains(keyword))
+                });
+            if offline {
+                return ApiErrorKind::DeviceOffline;
+            }
+        }
+
+        ApiErrorKind::from_code(self.code)
+    }
+
+    /// 在 `data` 中查找可读的错误描述。
+    ///
+    /// 出错时服务端有时会在 `data` 里附带比 `message` 更具体的说明，这里依次查找常见的字段名
+    /// （`description`、`desc`、`errorDescription`、`msg`），也会查找嵌套一层的对象。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::XiaoaiResponse;
+    /// let res = XiaoaiResponse {
+    ///     code: 101,
+    ///     message: "error".to_string(),
+    ///     data: serde_json::json!({"info": {"description": "设备不在线"}}),
+    /// };
+    /// assert_eq!(res.description(), Some("设备不在线"));
+    /// ```
+    pub fn description(&self) -> Option<&str> {
+        fn find(value: &Value) -> Option<&str> {
+            ["description", "desc", "errorDescription", "msg"]
+                .iter()
+                .filter_map(|key| value.get(key)?.as_str())
+                .find(|desc| !desc.is_empty())
+        }
+
+        find(&self.data).or_else(|| {
+            self.data
+                .as_object()?
+                .values()
+                .filter(|value| value.is_object())
+                .find_map(find)
+        })
+    }
+
     /// 提取响应的 `data` 并反序列化。
     ///
     /// # Errors
     ///
-    /// 当 `data` 不能反序列化为 `T` 时报错，详见 [`serde_json::from_value`]。
+    /// 当 `data` 不能反序列化为 `T` 时，将返回 [`Error::DataDecode`]，其中附带了（可能被截断的）原始数据。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::{Error, XiaoaiResponse};
+    /// let res = XiaoaiResponse {
+    ///     code: 0,
+    ///     message: "Success".to_string(),
+    ///     data: serde_json::json!({"volume": "loud"}),
+    /// };
+    /// let Err(Error::DataDecode { raw, .. }) = res.extract_data::<Vec<u32>>() else {
+    ///     panic!("应当解析失败");
+    /// };
+    /// assert_eq!(raw, r#"{"volume":"loud"}"#);
+    /// ```
     pub fn extract_data<T: DeserializeOwned>(self) -> crate::Result<T> {
-        Ok(serde_json::from_value(self.data)?)
+        T::deserialize(&self.data).map_err(|source| crate::Error::DataDecode {
+            source,
+            raw: util::truncate(&self.data.to_string(), MAX_RAW_DATA_CHARS),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn error_for_code() {
+        let ok: XiaoaiResponse =
+            serde_json::from_value(json!({"code": 0, "message": "Success", "data": null})).unwrap();
+        assert!(ok.error_for_code().is_ok());
+
+        let failed: XiaoaiResponse =
+            serde_json::from_value(json!({"code": "-7", "message": "error", "data": null})).unwrap();
+        match failed.error_for_code() {
+            Err(Error::Api(response)) => assert_eq!(response.code, -7),
+            other => panic!("应返回 Error::Api，实际为 {:?}", other),
+        }
     }
 }
@@ -1,18 +1,18 @@
 //! 登录小爱服务。
 
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use base64ct::{Base64, Encoding};
 use cookie_store::{CookieStore, RawCookie};
 use md5::{Digest, Md5};
-use reqwest::{Client, Url};
+use reqwest::{Client, Proxy, Url};
 use reqwest_cookie_store::CookieStoreMutex;
 use serde::Deserialize;
 use serde_json::Value;
 use sha1::Sha1;
 use tracing::trace;
 
-use crate::util::random_id;
+use crate::{client::ClientConfig, util::random_id};
 
 /// 登录小爱服务。
 ///
@@ -25,30 +25,17 @@ pub struct Login {
     username: String,
     password_hash: String,
     cookie_store: Arc<CookieStoreMutex>,
+    config: ClientConfig,
 }
 
 const LOGIN_SERVER: &str = "https://account.xiaomi.com/pass/";
-const LOGIN_UA: &str = "APP/com.xiaomi.mihome APPV/6.0.103 iosPassportSDK/3.9.0 iOS/14.4 miHSTS";
 
 impl Login {
     pub fn new(username: impl Into<String>, password: impl AsRef<[u8]>) -> crate::Result<Self> {
         let server = Url::parse(LOGIN_SERVER)?;
-
-        // 预先添加 Cookies
-        let mut cookie_store = CookieStore::new(None);
-        let device_id = random_device_id();
-        for (name, value) in [("sdkVersion", "3.9"), ("deviceId", &device_id)] {
-            let cookie = RawCookie::build((name, value)).path("/").build();
-            cookie_store.insert_raw(&cookie, &server)?;
-            trace!("预先添加 Cookies: {}", cookie);
-        }
-        let cookie_store = Arc::new(CookieStoreMutex::new(cookie_store));
-
-        // 用于登录的 Client
-        let client = Client::builder()
-            .cookie_provider(Arc::clone(&cookie_store))
-            .user_agent(LOGIN_UA)
-            .build()?;
+        let cookie_store = preset_cookie_store(&server)?;
+        let config = ClientConfig::default();
+        let client = config.login_client(&cookie_store)?;
 
         Ok(Self {
             client,
@@ -56,9 +43,83 @@ impl Login {
             username: username.into(),
             password_hash: hash_password(password),
             cookie_store,
+            config,
         })
     }
 
+    /// 使用自定义的登录服务器，默认为 `https://account.xiaomi.com/pass/`。
+    ///
+    /// 用于其他区域的账号，`server` 应以 `/` 结尾。需要在 [`login`][Login::login] 之前调用，
+    /// 否则已有的登录进度会被丢弃。
+    pub fn with_server(mut self, server: Url) -> crate::Result<Self> {
+        self.cookie_store = preset_cookie_store(&server)?;
+        self.client = self.config.login_client(&self.cookie_store)?;
+        self.server = server;
+
+        Ok(self)
+    }
+
+    /// 登录后请求小爱服务使用的 API 服务器，参见 [`Xiaoai::with_server`][crate::Xiaoai::with_server]。
+    ///
+    /// 通过 [`Xiaoai::from_login`][crate::Xiaoai::from_login] 构造的 `Xiaoai` 会使用此服务器。
+    pub fn with_api_server(mut self, server: Url) -> Self {
+        self.config.api_server = server;
+        self
+    }
+
+    /// 是否跳过 TLS 证书校验，默认不跳过。
+    ///
+    /// **危险**：仅用于通过 mitmproxy 等代理调试接口，跳过校验后连接可以被任意中间人窃听和篡改。
+    /// 通过 [`Xiaoai::from_login`][crate::Xiaoai::from_login] 构造的 `Xiaoai` 会沿用此设置。
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> crate::Result<Self> {
+        self.config.accept_invalid_certs = accept;
+        self.client = self.config.login_client(&self.cookie_store)?;
+
+        Ok(self)
+    }
+
+    /// 通过代理访问小米的服务。
+    ///
+    /// 不设置时会读取 `HTTP_PROXY`、`HTTPS_PROXY` 等环境变量。
+    /// 通过 [`Xiaoai::from_login`][crate::Xiaoai::from_login] 构造的 `Xiaoai` 会沿用此设置。
+    pub fn with_proxy(mut self, proxy: Proxy) -> crate::Result<Self> {
+        self.config.proxy = Some(proxy);
+        self.client = self.config.login_client(&self.cookie_store)?;
+
+        Ok(self)
+    }
+
+    /// 设置模拟的米家 APP 版本，默认为 [`DEFAULT_APP_VERSION`][crate::DEFAULT_APP_VERSION]。
+    ///
+    /// 版本会写入 User-Agent，旧版本被服务端弃用导致登录失败时，可以先用此方法换成较新的版本。
+    /// 通过 [`Xiaoai::from_login`][crate::Xiaoai::from_login] 构造的 `Xiaoai` 会沿用此设置。
+    pub fn app_version(mut self, version: impl Into<String>) -> crate::Result<Self> {
+        self.config.app_version = version.into();
+        self.client = self.config.login_client(&self.cookie_store)?;
+
+        Ok(self)
+    }
+
+    /// 覆盖登录请求的 User-Agent，默认模拟 iOS 上的米家 APP，版本取自 [`Login::app_version`]。
+    ///
+    /// 登录被风控拦截时，可以换一个 User-Agent 再试。只影响登录，不影响之后请求小爱服务的 User-Agent。
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> crate::Result<Self> {
+        self.config.login_user_agent = Some(user_agent.into());
+        self.client = self.config.login_client(&self.cookie_store)?;
+
+        Ok(self)
+    }
+
+    /// 设置请求的超时时间，默认为 [`DEFAULT_TIMEOUT`][crate::DEFAULT_TIMEOUT]。
+    ///
+    /// 通过 [`Xiaoai::from_login`][crate::Xiaoai::from_login] 构造的 `Xiaoai` 会沿用此设置。
+    pub fn timeout(mut self, timeout: Duration) -> crate::Result<Self> {
+        self.config.timeout = timeout;
+        self.client = self.config.login_client(&self.cookie_store)?;
+
+        Ok(self)
+    }
+
     /// 初步登录小爱服务。
     ///
     /// 结果中可能会出现登录失败的信息，但这无伤大雅，初步登录只是为了获取一些接下来认证所需的数据。
@@ -89,10 +150,19 @@ impl Login {
     /// 认证小爱服务。
     ///
     /// 需要使用初步登录的结果进行。
+    ///
+    /// # Errors
+    ///
+    /// - 账号密码错误、账号被风控等情况，将返回 [`Error::LoginFailed`][crate::Error::LoginFailed]。
+    /// - 需要输入图形验证码时，将返回 [`Error::NeedCaptcha`][crate::Error::NeedCaptcha]。
+    /// - 需要通过短信或邮箱进行二次验证时，将返回 [`Error::NeedVerification`][crate::Error::NeedVerification]，
+    ///   可以用 [`start_verification`][Login::start_verification] 继续。
     pub async fn auth(&self, login_response: LoginResponse) -> crate::Result<AuthResponse> {
         let raw = self.raw_auth(login_response).await?;
+        let response: AuthResponse = serde_json::from_value(raw)?;
+        response.check()?;
 
-        Ok(serde_json::from_value(raw)?)
+        Ok(response)
     }
 
     /// 同 [`Login::auth`]，但返回原始的 JSON。
@@ -125,6 +195,10 @@ impl Login {
     /// 获取小爱服务的 token，是登录的核心步骤。
     ///
     /// 需要在认证成功后进行。
+    ///
+    /// # Errors
+    ///
+    /// 请求结束后如果没有得到 `serviceToken`，将返回 [`Error::TokenNotObtained`][crate::Error::TokenNotObtained]。
     pub async fn get_token(&self, auth_response: AuthResponse) -> crate::Result<Value> {
         // 使用 notificationUrl 如果可用（新版API）
         let url_str = if let Some(notification_url) = &auth_response.notification_url {
@@ -162,13 +236,134 @@ impl Login {
             serde_json::from_str(&text).unwrap_or_else(|_| serde_json::json!({}))
         };
 
+        // serviceToken 通过重定向过程中的 Set-Cookie 写入，确认确实拿到了
+        if !self.has_service_token() {
+            return Err(crate::Error::TokenNotObtained);
+        }
+
         Ok(json_response)
     }
 
+    /// 开始二次验证，让服务端向账号绑定的手机或邮箱发送验证码。
+    ///
+    /// `notification_url` 取自 [`Error::NeedVerification`][crate::Error::NeedVerification]。返回验证码的发送方式，
+    /// 收到验证码后用 [`resolve_verification`][Login::resolve_verification] 完成登录。
+    ///
+    /// 流程参考社区逆向的结果，服务端调整后可能失效，此时可以在浏览器中打开 `notification_url` 手动验证。
+    pub async fn start_verification(&self, notification_url: &str) -> crate::Result<VerifyMethod> {
+        // authStart 换成 list 可以拿到可用的验证方式，同时写入后续请求需要的 Cookies
+        let list_url = Url::parse(&notification_url.replacen("authStart", "list", 1))?;
+        let bytes = self
+            .client
+            .get(list_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let response: Value = serde_json::from_slice(strip_json_prefix(&bytes))?;
+        trace!("获取二次验证方式: {response}");
+
+        let method = match response.get("flag").and_then(Value::as_i64) {
+            Some(8) => VerifyMethod::Email,
+            _ => VerifyMethod::Phone,
+        };
+        let send_path = match method {
+            VerifyMethod::Phone => "/identity/auth/sendPhoneTicket",
+            VerifyMethod::Email => "/identity/auth/sendEmailTicket",
+        };
+        let bytes = self
+            .client
+            .post(self.server.join(send_path)?)
+            .form(&[("_json", "true"), ("retry", "0"), ("icode", "")])
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let response: Value = serde_json::from_slice(strip_json_prefix(&bytes))?;
+        trace!("发送验证码: {response}");
+        check_identity_response(&response)?;
+
+        Ok(method)
+    }
+
+    /// 提交验证码完成二次验证，并获取 `serviceToken`。
+    ///
+    /// 成功后即可使用 [`Xiaoai::from_login`][crate::Xiaoai::from_login]，不需要再调用
+    /// [`get_token`][Login::get_token]。
+    ///
+    /// # Errors
+    ///
+    /// - 验证码错误时，将返回 [`Error::LoginFailed`][crate::Error::LoginFailed]。
+    /// - 验证通过但没有拿到 `serviceToken` 时，将返回 [`Error::TokenNotObtained`][crate::Error::TokenNotObtained]。
+    pub async fn resolve_verification(&self, method: VerifyMethod, ticket: &str) -> crate::Result<()> {
+        let (verify_path, flag) = match method {
+            VerifyMethod::Phone => ("/identity/auth/verifyPhone", "4"),
+            VerifyMethod::Email => ("/identity/auth/verifyEmail", "8"),
+        };
+        let bytes = self
+            .client
+            .post(self.server.join(verify_path)?)
+            .form(&[
+                ("_json", "true"),
+                ("_flag", flag),
+                ("ticket", ticket.trim()),
+                ("trust", "true"),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .bytes()
+            .await?;
+        let response: Value = serde_json::from_slice(strip_json_prefix(&bytes))?;
+        trace!("提交验证码: {response}");
+        check_identity_response(&response)?;
+
+        // 跟随 location 的重定向，serviceToken 会在这个过程中写入
+        let location = response
+            .get("location")
+            .and_then(Value::as_str)
+            .ok_or(crate::Error::TokenNotObtained)?;
+        self.client
+            .get(Url::parse(location)?)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        if !self.has_service_token() {
+            return Err(crate::Error::TokenNotObtained);
+        }
+
+        Ok(())
+    }
+
+    /// 是否已经获得了未过期的 `serviceToken`。
+    fn has_service_token(&self) -> bool {
+        self.cookie_store
+            .lock()
+            .unwrap()
+            .iter_unexpired()
+            .any(|cookie| cookie.name() == "serviceToken")
+    }
+
     /// 消耗 `Login` 并提取 Cookies，其中存储了当前的登录状态。
     pub fn into_cookie_store(self) -> Arc<CookieStoreMutex> {
         self.cookie_store
     }
+
+    /// 沿用 [`Xiaoai`][crate::Xiaoai] 的 `Client` 设置，用于自动重新登录。
+    pub(crate) fn with_config(mut self, config: ClientConfig) -> crate::Result<Self> {
+        self.client = config.login_client(&self.cookie_store)?;
+        self.config = config;
+
+        Ok(self)
+    }
+
+    /// 消耗 `Login`，提取 Cookies 和 `Client` 设置。
+    pub(crate) fn into_parts(self) -> (Arc<CookieStoreMutex>, ClientConfig) {
+        (self.cookie_store, self.config)
+    }
 }
 
 /// [`Login::login`] 的响应体，但仅包含 [`Login::auth`] 所需的字段。
@@ -183,6 +378,18 @@ pub struct LoginResponse {
 /// [`Login::auth`] 的响应体，但仅包含 [`Login::get_token`] 所需的字段。
 #[derive(Clone, Deserialize, Debug)]
 pub struct AuthResponse {
+    /// 错误码，`0` 表示认证通过。
+    #[serde(default, deserialize_with = "crate::util::deserialize_code")]
+    pub code: i64,
+    /// 出错时的说明。
+    #[serde(default)]
+    pub description: Option<String>,
+    /// 出错时的简短说明，部分响应只有这个字段。
+    #[serde(default)]
+    pub desc: Option<String>,
+    /// 需要输入图形验证码时的验证码地址。
+    #[serde(rename = "captchaUrl", default)]
+    pub captcha_url: Option<String>,
     #[serde(default)]
     pub location: String,
     #[serde(default)]
@@ -193,6 +400,76 @@ pub struct AuthResponse {
     pub notification_url: Option<String>,
 }
 
+/// 创建预先添加了登录所需 Cookies 的 cookie_store。
+fn preset_cookie_store(server: &Url) -> crate::Result<Arc<CookieStoreMutex>> {
+    let mut cookie_store = CookieStore::new(None);
+    let device_id = random_device_id();
+    for (name, value) in [("sdkVersion", "3.9"), ("deviceId", &device_id)] {
+        let cookie = RawCookie::build((name, value)).path("/").build();
+        cookie_store.insert_raw(&cookie, server)?;
+        trace!("预先添加 Cookies: {}", cookie);
+    }
+
+    Ok(Arc::new(CookieStoreMutex::new(cookie_store)))
+}
+
+/// 二次验证的方式。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerifyMethod {
+    /// 短信验证码。
+    Phone,
+    /// 邮箱验证码。
+    Email,
+}
+
+/// 带 `_json=true` 的响应会以 `&&&START&&&` 开头，去掉它才是 json。
+fn strip_json_prefix(bytes: &[u8]) -> &[u8] {
+    bytes.strip_prefix(b"&&&START&&&").unwrap_or(bytes)
+}
+
+/// 检查二次验证相关接口的 `code`。
+fn check_identity_response(response: &Value) -> crate::Result<()> {
+    let code = response.get("code").and_then(Value::as_i64).unwrap_or(0);
+    if code == 0 {
+        return Ok(());
+    }
+
+    let reason = ["tips", "description", "desc"]
+        .iter()
+        .find_map(|key| response.get(key)?.as_str())
+        .unwrap_or_default()
+        .to_string();
+    Err(crate::Error::LoginFailed { code, reason })
+}
+
+impl AuthResponse {
+    /// 检查认证是否真正通过。
+    fn check(&self) -> crate::Result<()> {
+        if self.code != 0 {
+            return Err(crate::Error::LoginFailed {
+                code: self.code,
+                reason: self
+                    .description
+                    .clone()
+                    .or_else(|| self.desc.clone())
+                    .unwrap_or_default(),
+            });
+        }
+        if let Some(url) = self.captcha_url.as_ref().filter(|url| !url.is_empty()) {
+            return Err(crate::Error::NeedCaptcha { url: url.clone() });
+        }
+        // 需要二次验证（如短信验证）时，没有 location，只给出验证页面的 notificationUrl
+        if self.location.is_empty() {
+            if let Some(url) = self.notification_url.as_ref().filter(|url| !url.is_empty()) {
+                return Err(crate::Error::NeedVerification { url: url.clone() });
+            }
+        }
+
+        Ok(())
+    }
+}
+
 fn random_device_id() -> String {
     let mut device_id = random_id(16);
     device_id.make_ascii_uppercase();
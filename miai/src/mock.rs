@@ -0,0 +1,217 @@
+//! 用于测试的 [`MockXiaoai`]，需要开启 `mock` feature。
+//!
+//! # 示例
+//!
+//! ```
+//! use miai::{XiaoaiResponse, api::XiaoaiApi, mock::MockXiaoai};
+//!
+//! # #[tokio::main(flavor = "current_thread")]
+//! # async fn main() {
+//! let mock = MockXiaoai::new().with_volume(30).with_response(
+//!     "play_url",
+//!     XiaoaiResponse { code: 500, message: "error".to_string(), data: serde_json::Value::Null },
+//! );
+//!
+//! mock.tts("device", "你好").await.unwrap();
+//! mock.set_volume("device", 50).await.unwrap();
+//! assert_eq!(mock.get_volume("device").await.unwrap(), 50);
+//! assert!(mock.play_url("device", "http://example.com/a.mp3").await.is_err());
+//!
+//! let calls = mock.calls();
+//! assert_eq!(calls[0].method, "tts");
+//! assert_eq!(calls[0].args, ["device", "你好"]);
+//! assert_eq!(calls.len(), 4);
+//! # }
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+};
+
+use serde_json::Value;
+
+use crate::{
+    DeviceInfo, PlayState, XiaoaiResponse,
+    api::{BoxFuture, XiaoaiApi},
+};
+
+/// [`MockXiaoai`] 记录的一次调用。
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Call {
+    /// [`XiaoaiApi`] 的方法名，如 `tts`
+    pub method: &'static str,
+    /// 调用的参数，按顺序转换为字符串
+    pub args: Vec<String>,
+}
+
+/// 实现了 [`XiaoaiApi`] 的假设备，记录每次调用并返回预设的结果，不发送网络请求。
+///
+/// 没有预设响应的方法返回 `code` 为 0 的成功响应；预设响应的 `code` 不为 0 时返回
+/// [`Error::Api`][crate::Error::Api]，可以用来模拟请求失败。
+/// [`XiaoaiApi::set_volume`] 会修改 [`XiaoaiApi::get_volume`] 读到的音量。
+#[derive(Debug, Default)]
+pub struct MockXiaoai {
+    state: Mutex<State>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    calls: Vec<Call>,
+    devices: Vec<DeviceInfo>,
+    volume: u32,
+    responses: HashMap<&'static str, XiaoaiResponse>,
+}
+
+impl MockXiaoai {
+    /// 没有设备、音量为 0 的 mock。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置 [`XiaoaiApi::device_info`] 返回的设备。
+    pub fn with_devices(self, devices: Vec<DeviceInfo>) -> Self {
+        self.state().devices = devices;
+        self
+    }
+
+    /// 设置初始音量。
+    pub fn with_volume(self, volume: u32) -> Self {
+        self.state().volume = volume;
+        self
+    }
+
+    /// 设置 `method` 返回的响应，`method` 为 [`XiaoaiApi`] 的方法名，如 `tts`。
+    pub fn with_response(self, method: &'static str, response: XiaoaiResponse) -> Self {
+        self.state().responses.insert(method, response);
+        self
+    }
+
+    /// 目前为止的所有调用，按调用顺序排列。
+    pub fn calls(&self) -> Vec<Call> {
+        self.state().calls.clone()
+    }
+
+    /// 清空调用记录。
+    pub fn clear_calls(&self) {
+        self.state().calls.clear();
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    /// 记录一次调用，返回 `method` 的预设响应。
+    fn record(&self, method: &'static str, args: &[&str]) -> crate::Result<XiaoaiResponse> {
+        let mut state = self.state();
+        state.calls.push(Call {
+            method,
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        });
+
+        state
+            .responses
+            .get(method)
+            .cloned()
+            .unwrap_or_else(|| XiaoaiResponse {
+                code: 0,
+                message: "Success".to_string(),
+                data: Value::Null,
+            })
+            .error_for_code()
+    }
+
+    fn respond(
+        &self,
+        method: &'static str,
+        args: &[&str],
+    ) -> BoxFuture<'static, crate::Result<XiaoaiResponse>> {
+        let result = self.record(method, args);
+        Box::pin(async move { result })
+    }
+}
+
+impl XiaoaiApi for MockXiaoai {
+    fn device_info(&self) -> BoxFuture<'_, crate::Result<Vec<DeviceInfo>>> {
+        let result = self
+            .record("device_info", &[])
+            .map(|_| self.state().devices.clone());
+        Box::pin(async move { result })
+    }
+
+    fn ubus_call<'a>(
+        &'a self,
+        device_id: &'a str,
+        path: &'a str,
+        method: &'a str,
+        message: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        self.respond("ubus_call", &[device_id, path, method, message])
+    }
+
+    fn tts<'a>(
+        &'a self,
+        device_id: &'a str,
+        text: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        self.respond("tts", &[device_id, text])
+    }
+
+    fn play_url<'a>(
+        &'a self,
+        device_id: &'a str,
+        url: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        self.respond("play_url", &[device_id, url])
+    }
+
+    fn set_volume<'a>(
+        &'a self,
+        device_id: &'a str,
+        volume: u32,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        let result = self
+            .record("set_volume", &[device_id, &volume.to_string()])
+            .and_then(|response| {
+                if crate::model::VOLUME_RANGE.contains(&volume) {
+                    Ok(response)
+                } else {
+                    Err(crate::Error::InvalidVolume(volume))
+                }
+            });
+        if result.is_ok() {
+            self.state().volume = volume;
+        }
+        Box::pin(async move { result })
+    }
+
+    fn get_volume<'a>(&'a self, device_id: &'a str) -> BoxFuture<'a, crate::Result<u32>> {
+        let result = self
+            .record("get_volume", &[device_id])
+            .map(|_| self.state().volume);
+        Box::pin(async move { result })
+    }
+
+    fn set_play_state<'a>(
+        &'a self,
+        device_id: &'a str,
+        state: PlayState,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        self.respond("set_play_state", &[device_id, &format!("{:?}", state)])
+    }
+
+    fn nlp<'a>(
+        &'a self,
+        device_id: &'a str,
+        text: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        self.respond("nlp", &[device_id, text])
+    }
+
+    fn player_status<'a>(
+        &'a self,
+        device_id: &'a str,
+    ) -> BoxFuture<'a, crate::Result<XiaoaiResponse>> {
+        self.respond("player_status", &[device_id])
+    }
+}
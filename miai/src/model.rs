@@ -0,0 +1,83 @@
+//! 已知机型及其特性。
+//!
+//! 不同机型对同一个接口的表现并不一致，这里集中记录社区（[MiService]、[xiaomusic]）
+//! 逆向得到的经验。只收录有出处的信息，未收录的机型使用 [`DEFAULT_MODEL`]。
+//! 发现新的机型特性时，在 [`MODELS`] 中追加并注明出处即可。
+//!
+//! 目前没有收集到任何机型的音量范围与 `0..=100` 不同，因此这里不记录音量，
+//! 统一使用 [`VOLUME_RANGE`]。
+//!
+//! [MiService]: https://github.com/yihong0618/MiService
+//! [xiaomusic]: https://github.com/hanxi/xiaomusic
+
+use std::ops::RangeInclusive;
+
+/// 机型信息。
+#[derive(Clone, Copy, Debug)]
+pub struct ModelInfo {
+    /// 机型代码，对应 [`DeviceInfo::hardware`][crate::DeviceInfo::hardware]。
+    pub hardware: &'static str,
+
+    /// 播放链接时推荐的方法，[`Xiaoai::play_url_for`][crate::Xiaoai::play_url_for] 据此选择。
+    pub play_method: PlayMethod,
+}
+
+/// 播放链接的方法。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PlayMethod {
+    /// 使用 [`Xiaoai::play_url`][crate::Xiaoai::play_url]。
+    Url,
+    /// 使用 [`Xiaoai::play_music`][crate::Xiaoai::play_music]。
+    Music,
+}
+
+/// `player_play_url` 的 `type` 字段的默认取值。
+///
+/// 该字段会影响亮灯和暂停行为，各机型的表现并不一致，但目前没有按机型整理的数据，
+/// 参考 <https://github.com/yihong0618/MiService/issues/30>。
+pub const DEFAULT_PLAY_URL_TYPE: u32 = 3;
+
+/// 音量的取值范围。
+///
+/// 目前收录的资料中没有使用其他刻度的机型，发现后再改为按机型记录。
+pub const VOLUME_RANGE: RangeInclusive<u32> = 0..=100;
+
+const fn music_model(hardware: &'static str) -> ModelInfo {
+    ModelInfo {
+        hardware,
+        play_method: PlayMethod::Music,
+    }
+}
+
+/// 已知机型的列表。
+pub static MODELS: &[ModelInfo] = &[
+    // 以下机型使用 play_url 无法正常播放，需要改用 play_music，
+    // 来自 xiaomusic 的 NEED_USE_PLAY_MUSIC_API
+    music_model("X08C"),
+    music_model("X08E"),
+    music_model("X8F"),
+    music_model("X4B"),
+    music_model("LX05"),
+    music_model("OH2"),
+    music_model("OH2P"),
+    music_model("X6A"),
+];
+
+/// 未收录的机型使用的默认特性，与 [`Xiaoai::play_url`][crate::Xiaoai::play_url] 的默认行为一致。
+pub static DEFAULT_MODEL: ModelInfo = ModelInfo {
+    hardware: "",
+    play_method: PlayMethod::Url,
+};
+
+/// 列出所有已知机型。
+pub fn supported_models() -> &'static [ModelInfo] {
+    MODELS
+}
+
+/// 按机型代码查找机型信息，忽略 ASCII 大小写。
+pub fn model_info(hardware: &str) -> Option<&'static ModelInfo> {
+    MODELS
+        .iter()
+        .find(|model| model.hardware.eq_ignore_ascii_case(hardware))
+}
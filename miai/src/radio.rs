@@ -0,0 +1,47 @@
+//! 内置的常用网络电台列表。
+//!
+//! 列表保持精简，只收录长期稳定的直播流。需要增加电台时，在 [`STATIONS`] 中追加一项即可。
+
+/// 一个网络电台。
+#[derive(Clone, Copy, Debug)]
+pub struct Station {
+    /// 简短的标识，用于命令行等场景。
+    pub id: &'static str,
+
+    /// 电台名称。
+    pub name: &'static str,
+
+    /// 直播流地址。
+    pub url: &'static str,
+}
+
+/// 内置的电台列表。
+pub static STATIONS: &[Station] = &[
+    Station {
+        id: "zgzs",
+        name: "中国之声",
+        url: "http://ngcdn001.cnr.cn/live/zgzs/index.m3u8",
+    },
+    Station {
+        id: "jjzs",
+        name: "经济之声",
+        url: "http://ngcdn002.cnr.cn/live/jjzs/index.m3u8",
+    },
+    Station {
+        id: "yyzs",
+        name: "音乐之声",
+        url: "http://ngcdn003.cnr.cn/live/yyzs/index.m3u8",
+    },
+    Station {
+        id: "bbc",
+        name: "BBC World Service",
+        url: "http://stream.live.vc.bbcmedia.co.uk/bbc_world_service",
+    },
+];
+
+/// 按标识或名称查找电台，忽略 ASCII 大小写。
+pub fn find_station(name: &str) -> Option<&'static Station> {
+    STATIONS.iter().find(|station| {
+        station.id.eq_ignore_ascii_case(name) || station.name.eq_ignore_ascii_case(name)
+    })
+}
@@ -0,0 +1,39 @@
+//! 定时停止播放（睡眠定时器）。
+//!
+//! 小米没有公开定时关闭的接口，和 [`dnd`][crate::dnd] 一样，这里把设置转换为一条语音指令，
+//! 再通过 [`Xiaoai::nlp`][crate::Xiaoai::nlp] 交给小爱执行。计时由设备完成，调用方退出后依然有效，
+//! 参见 [`Xiaoai::set_sleep_timer`][crate::Xiaoai::set_sleep_timer]。
+//!
+//! 不支持语音定时关闭的设备，可以改用 [`Xiaoai::stop_after`][crate::Xiaoai::stop_after]
+//! 在本地计时，到时后停止播放；这种方式要求调用方一直运行到计时结束。
+//!
+//! # 示例
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use miai::sleep_timer;
+//!
+//! assert_eq!(sleep_timer::command(Duration::from_secs(30 * 60)), "30分钟后停止播放");
+//! assert_eq!(sleep_timer::command(Duration::from_secs(90 * 60)), "1小时30分钟后停止播放");
+//! assert_eq!(sleep_timer::command(Duration::from_secs(2 * 3600)), "2小时后停止播放");
+//! // 不足一分钟的部分向上取整
+//! assert_eq!(sleep_timer::command(Duration::from_secs(10)), "1分钟后停止播放");
+//! ```
+
+use std::time::Duration;
+
+use crate::time;
+
+/// 取消定时关闭的语音指令。
+pub const CANCEL_COMMAND: &str = "取消定时关闭";
+
+/// 查询定时关闭剩余时间的语音指令。
+pub const QUERY_COMMAND: &str = "定时关闭还剩多久";
+
+/// `after` 后停止播放的语音指令。
+///
+/// 时长的说法见 [`time::spoken_duration`]，不足一分钟的部分向上取整。
+pub fn command(after: Duration) -> String {
+    format!("{}后停止播放", time::spoken_duration(after))
+}
@@ -0,0 +1,35 @@
+//! 音频输入源。
+//!
+//! 和 [`alarm`][crate::alarm] 一样，小米没有公开切换输入源的接口，这里把切换转换为一条语音指令，
+//! 再通过 [`Xiaoai::nlp`][crate::Xiaoai::nlp] 交给小爱执行。只收录有对应语音指令的输入源，
+//! 没有语音指令的输入源（如 AUX）无法切换。
+//!
+//! # 示例
+//!
+//! ```
+//! use miai::source::{self, Source};
+//!
+//! assert_eq!(source::command(Source::Bluetooth), "打开蓝牙");
+//! assert_eq!(source::command(Source::Internal), "关闭蓝牙");
+//! ```
+
+/// 查询当前输入源的语音指令。
+pub const QUERY_COMMAND: &str = "蓝牙开着吗";
+
+/// 音频输入源。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Source {
+    /// 音箱自身的播放器。
+    Internal,
+    /// 作为蓝牙音箱使用。
+    Bluetooth,
+}
+
+/// 切换到 `source` 的语音指令。
+pub fn command(source: Source) -> &'static str {
+    match source {
+        Source::Internal => "关闭蓝牙",
+        Source::Bluetooth => "打开蓝牙",
+    }
+}
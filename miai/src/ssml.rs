@@ -0,0 +1,81 @@
+//! 把 SSML 的一个子集转换为小爱能正确朗读的纯文本。
+//!
+//! 小爱的 `text_to_speech` 只接受纯文本，这里在本地做转换：
+//!
+//! - `<break/>` 转换为标点停顿，`time` 不小于 1 秒或 `strength` 为 `strong`/`x-strong` 时
+//!   使用 `。`，否则使用 `，`。
+//! - 其他标签（`<speak>`、`<emphasis>`、`<prosody>` 等）会被去掉，只保留其中的文本。
+//! - 常见的 XML 实体（`&lt;`、`&amp;` 等）会被还原。
+
+/// 把 SSML 转换为纯文本。
+///
+/// # Examples
+///
+/// ```
+/// use miai::ssml;
+///
+/// let text = ssml::to_text(r#"<speak>你好<break time="300ms"/>我在<break time="1s"/><emphasis>这里</emphasis></speak>"#);
+/// assert_eq!(text, "你好，我在。这里");
+///
+/// // 换行、emoji 和引号会原样保留
+/// assert_eq!(ssml::to_text("第一行\n第二行 🎉 &quot;引号&quot;"), "第一行\n第二行 🎉 \"引号\"");
+/// ```
+pub fn to_text(ssml: &str) -> String {
+    let mut text = String::with_capacity(ssml.len());
+    let mut rest = ssml;
+    while let Some(start) = rest.find('<') {
+        text.push_str(&decode_entities(&rest[..start]));
+        let Some(len) = rest[start..].find('>') else {
+            // 未闭合的 `<` 按原文处理
+            rest = &rest[start..];
+            break;
+        };
+        if let Some(pause) = break_pause(&rest[start + 1..start + len]) {
+            text.push(pause);
+        }
+        rest = &rest[start + len + 1..];
+    }
+    text.push_str(&decode_entities(rest));
+
+    text
+}
+
+/// 如果 `tag` 是 `break` 标签，返回对应的停顿标点。
+fn break_pause(tag: &str) -> Option<char> {
+    let attrs = tag.trim().trim_end_matches('/').strip_prefix("break")?;
+    if !attrs.is_empty() && !attrs.starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let long = attr_value(attrs, "strength").is_some_and(|s| s.ends_with("strong"))
+        || attr_value(attrs, "time")
+            .and_then(parse_millis)
+            .is_some_and(|ms| ms >= 1000.0);
+
+    Some(if long { '。' } else { '，' })
+}
+
+fn attr_value<'a>(attrs: &'a str, name: &str) -> Option<&'a str> {
+    let (_, value) = attrs.split_once(&format!("{name}="))?;
+    let quote = value.chars().next().filter(|c| *c == '"' || *c == '\'')?;
+    let value = &value[1..];
+
+    Some(&value[..value.find(quote)?])
+}
+
+/// 解析 `500ms`、`1s`、`1.5s` 形式的时长为毫秒。
+fn parse_millis(time: &str) -> Option<f64> {
+    if let Some(ms) = time.strip_suffix("ms") {
+        ms.trim().parse().ok()
+    } else {
+        time.strip_suffix('s')?.trim().parse::<f64>().ok().map(|s| s * 1000.0)
+    }
+}
+
+fn decode_entities(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
@@ -0,0 +1,71 @@
+//! [`alarm`][crate::alarm]、[`dnd`][crate::dnd] 和 [`sleep_timer`][crate::sleep_timer]
+//! 共用的时间表示，以及转换为语音指令中的说法。
+//!
+//! # 示例
+//!
+//! ```
+//! use std::time::Duration;
+//!
+//! use miai::time::{self, Time};
+//!
+//! assert_eq!(Time::new(7, 30).unwrap().to_string(), "7点30分");
+//! assert_eq!(Time::new(23, 0).unwrap().to_string(), "23点");
+//! assert!(Time::new(24, 0).is_err());
+//!
+//! assert_eq!(time::spoken_duration(Duration::from_secs(90 * 60)), "1小时30分钟");
+//! ```
+
+use std::{fmt, time::Duration};
+
+/// 一天中的时间，使用 24 小时制。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Time {
+    hour: u8,
+    minute: u8,
+}
+
+impl Time {
+    /// `hour` 点 `minute` 分。
+    ///
+    /// # Errors
+    ///
+    /// 时间无效时，将返回 [`Error::InvalidTime`][crate::Error::InvalidTime]。
+    pub fn new(hour: u8, minute: u8) -> crate::Result<Self> {
+        if hour >= 24 || minute >= 60 {
+            return Err(crate::Error::InvalidTime { hour, minute });
+        }
+
+        Ok(Self { hour, minute })
+    }
+
+    pub fn hour(self) -> u8 {
+        self.hour
+    }
+
+    pub fn minute(self) -> u8 {
+        self.minute
+    }
+}
+
+/// 按小爱能听懂的方式显示，如 `7点`、`7点30分`。
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.minute == 0 {
+            write!(f, "{}点", self.hour)
+        } else {
+            write!(f, "{}点{}分", self.hour, self.minute)
+        }
+    }
+}
+
+/// 按小爱能听懂的方式说出时长，如 `30分钟`、`1小时30分钟`。
+///
+/// 小爱只能理解到分钟，不足一分钟的部分向上取整，最短为一分钟。
+pub fn spoken_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs().div_ceil(60).max(1);
+    match (minutes / 60, minutes % 60) {
+        (0, minutes) => format!("{}分钟", minutes),
+        (hours, 0) => format!("{}小时", hours),
+        (hours, minutes) => format!("{}小时{}分钟", hours, minutes),
+    }
+}
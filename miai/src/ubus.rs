@@ -0,0 +1,93 @@
+//! 构造 [`Xiaoai::ubus_call`][crate::Xiaoai::ubus_call] 的 `message`。
+//!
+//! 常见的字段都有对应的方法，其他字段可以用 [`UbusMessage::field`] 添加，避免手写 JSON 时拼错字段名。
+//!
+//! # 示例
+//!
+//! ```
+//! use miai::ubus::UbusMessage;
+//!
+//! let message = UbusMessage::media_player().volume(30);
+//! let value: serde_json::Value = serde_json::from_str(&message.to_json_string()).unwrap();
+//! assert_eq!(value, serde_json::json!({"media": "app_ios", "volume": 30}));
+//!
+//! let message = UbusMessage::new().text("你好").field("speed", 1.5);
+//! let value: serde_json::Value = serde_json::from_str(&message.to_json_string()).unwrap();
+//! assert_eq!(value, serde_json::json!({"text": "你好", "speed": 1.5}));
+//! ```
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// 控制 `mediaplayer` 时 `media` 字段的取值。
+pub const APP_MEDIA: &str = "app_ios";
+
+/// ubus 调用的消息，未设置的字段不会出现在 JSON 中。
+///
+/// 所有字段保存在同一个 JSON 对象里，同一个键重复设置时后设置的值覆盖先前的值，
+/// 因此 JSON 中的键总是唯一的。
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(transparent)]
+pub struct UbusMessage {
+    fields: Map<String, Value>,
+}
+
+impl UbusMessage {
+    /// 空的消息。
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 控制 `mediaplayer` 的消息，`media` 为 [`APP_MEDIA`]。
+    pub fn media_player() -> Self {
+        Self::new().media(APP_MEDIA)
+    }
+
+    /// 设置 `media` 字段。
+    pub fn media(self, media: impl Into<String>) -> Self {
+        self.field("media", media.into())
+    }
+
+    /// 设置 `action` 字段，如 `player_play_operation` 的 `play`、`pause`。
+    pub fn action(self, action: impl Into<String>) -> Self {
+        self.field("action", action.into())
+    }
+
+    /// 设置 `volume` 字段。
+    pub fn volume(self, volume: u32) -> Self {
+        self.field("volume", volume)
+    }
+
+    /// 设置 `url` 字段。
+    pub fn url(self, url: impl Into<String>) -> Self {
+        self.field("url", url.into())
+    }
+
+    /// 设置 `type` 字段，如 `player_play_url` 的播放类型、`player_set_loop` 的循环模式。
+    pub fn kind(self, kind: u32) -> Self {
+        self.field("type", kind)
+    }
+
+    /// 设置 `text` 字段。
+    pub fn text(self, text: impl Into<String>) -> Self {
+        self.field("text", text.into())
+    }
+
+    /// 设置任意字段，已经设置过的字段会被覆盖。
+    ///
+    /// ```
+    /// use miai::ubus::UbusMessage;
+    ///
+    /// let message = UbusMessage::new().volume(30).field("volume", 50);
+    /// assert_eq!(message.to_json_string(), r#"{"volume":50}"#);
+    /// ```
+    pub fn field(mut self, key: impl Into<String>, value: impl Into<Value>) -> Self {
+        self.fields.insert(key.into(), value.into());
+        self
+    }
+
+    /// 序列化为 JSON 字符串，作为 [`Xiaoai::ubus_call`][crate::Xiaoai::ubus_call] 的 `message`。
+    pub fn to_json_string(&self) -> String {
+        serde_json::to_string(self).expect("UbusMessage 的字段都可以序列化为 JSON")
+    }
+}
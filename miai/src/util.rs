@@ -1,8 +1,115 @@
 use rand::{
+    Rng,
     distr::{Alphanumeric, SampleString},
     rng,
 };
+use serde::{Deserialize, Deserializer, de::Error};
+use serde_json::Value;
+
+/// 请求小爱服务时 requestId 的前缀。
+pub const REQUEST_ID_PREFIX: &str = "app_ios_";
 
 pub fn random_id(len: usize) -> String {
-    Alphanumeric.sample_string(&mut rng(), len)
+    random_id_with(&mut rng(), len)
+}
+
+/// 同 [`random_id`]，但使用传入的 `rng`，传入固定种子的 rng 时结果是确定的。
+pub fn random_id_with<R: Rng + ?Sized>(rng: &mut R, len: usize) -> String {
+    Alphanumeric.sample_string(rng, len)
+}
+
+/// 生成请求小爱服务所用的 requestId。
+pub fn random_request_id() -> String {
+    random_request_id_with(&mut rng())
+}
+
+/// 同 [`random_request_id`]，但使用传入的 `rng`。
+///
+/// 结果为 [`REQUEST_ID_PREFIX`] 加上 30 位随机的字母和数字。
+pub fn random_request_id_with<R: Rng + ?Sized>(rng: &mut R) -> String {
+    let mut request_id = random_id_with(rng, 30);
+    request_id.insert_str(0, REQUEST_ID_PREFIX);
+
+    request_id
+}
+
+/// 反序列化响应中的 `code`，服务端偶尔会把它返回成字符串。
+pub fn deserialize_code<'de, D: Deserializer<'de>>(deserializer: D) -> Result<i64, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Number(n) => n
+            .as_i64()
+            .ok_or_else(|| D::Error::custom(format!("code 不是整数: {n}"))),
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map_err(|_| D::Error::custom(format!("code 不是整数: {s}"))),
+        other => Err(D::Error::custom(format!("code 类型错误: {other}"))),
+    }
+}
+
+/// 截断到最多 `max_chars` 个字符，截断时末尾加上 `…`。
+pub fn truncate(text: &str, max_chars: usize) -> String {
+    match text.char_indices().nth(max_chars) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{SeedableRng, rngs::StdRng};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn seeded_request_id() {
+        let request_id = random_request_id_with(&mut StdRng::seed_from_u64(42));
+        assert!(request_id.starts_with(REQUEST_ID_PREFIX));
+        assert_eq!(request_id.len(), REQUEST_ID_PREFIX.len() + 30);
+        assert!(request_id[REQUEST_ID_PREFIX.len()..].chars().all(|c| c.is_ascii_alphanumeric()));
+
+        // 同一种子生成的结果相同
+        assert_eq!(request_id, random_request_id_with(&mut StdRng::seed_from_u64(42)));
+        assert_ne!(request_id, random_request_id_with(&mut StdRng::seed_from_u64(7)));
+    }
+
+    #[test]
+    fn seeded_random_id() {
+        let id = random_id_with(&mut StdRng::seed_from_u64(1), 16);
+        assert_eq!(id.len(), 16);
+        assert_eq!(id, random_id_with(&mut StdRng::seed_from_u64(1), 16));
+        assert_eq!(random_id_with(&mut StdRng::seed_from_u64(1), 0), "");
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Response {
+        #[serde(deserialize_with = "deserialize_code")]
+        code: i64,
+    }
+
+    fn parse(code: Value) -> Result<i64, serde_json::Error> {
+        serde_json::from_value::<Response>(json!({ "code": code })).map(|response| response.code)
+    }
+
+    #[test]
+    fn deserialize_integer_code() {
+        assert_eq!(parse(json!(0)).unwrap(), 0);
+        assert_eq!(parse(json!(101)).unwrap(), 101);
+        assert_eq!(parse(json!(-7)).unwrap(), -7);
+    }
+
+    #[test]
+    fn deserialize_string_code() {
+        assert_eq!(parse(json!("0")).unwrap(), 0);
+        assert_eq!(parse(json!(" 401 ")).unwrap(), 401);
+    }
+
+    #[test]
+    fn reject_invalid_code() {
+        assert!(parse(json!(1.5)).is_err());
+        assert!(parse(json!("abc")).is_err());
+        assert!(parse(json!(null)).is_err());
+        assert!(parse(json!([0])).is_err());
+    }
 }
@@ -2,12 +2,14 @@
 //!
 //! 实现了类似 mi-gpt 的动态间隔轮询和关键词匹配机制。
 
-use std::collections::HashSet;
-use std::time::Duration;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::{Duration, Instant};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
 use tracing::{debug, info, trace, warn};
 
-use crate::{Xiaoai, Conversation};
+use crate::{Xiaoai, XiaoaiResponse, Conversation};
 
 /// 关键词配置。
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -26,6 +28,62 @@ pub struct KeywordConfig {
     /// 关键词描述（用于日志和调试）
     #[serde(default)]
     pub description: String,
+
+    /// 命中后依次执行的动作
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub actions: Vec<KeywordAction>,
+}
+
+/// 关键词命中后执行的动作。
+///
+/// 配置中写作 `{"type": "tts", "text": "好的"}`，可以额外用 `device_id` 指定目标设备。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KeywordAction {
+    /// 目标设备 ID，默认为被监听的设备
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_id: Option<String>,
+
+    /// 动作的类型和参数
+    #[serde(flatten)]
+    pub kind: ActionKind,
+}
+
+/// 动作的类型和参数。
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ActionKind {
+    /// 播报文本，参见 [`Xiaoai::tts`]
+    Tts { text: String },
+    /// 播放链接，参见 [`Xiaoai::play_url`]
+    PlayUrl { url: String },
+    /// 设置音量，参见 [`Xiaoai::set_volume`]
+    Volume { volume: u32 },
+    /// 让小爱执行文本，参见 [`Xiaoai::nlp`]
+    Nlp { text: String },
+}
+
+impl KeywordAction {
+    /// 执行动作，未指定设备时使用 `default_device`。
+    async fn run(&self, xiaoai: &Xiaoai, default_device: &str) -> crate::Result<XiaoaiResponse> {
+        let device_id = self.device_id.as_deref().unwrap_or(default_device);
+        match &self.kind {
+            ActionKind::Tts { text } => xiaoai.tts(device_id, text).await,
+            ActionKind::PlayUrl { url } => xiaoai.play_url(device_id, url).await,
+            ActionKind::Volume { volume } => xiaoai.set_volume(device_id, *volume).await,
+            ActionKind::Nlp { text } => xiaoai.nlp(device_id, text).await,
+        }
+    }
+}
+
+/// 动作的执行结果。
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct ActionResult {
+    /// 执行的动作
+    pub action: KeywordAction,
+    /// 成功时为小爱服务的响应，失败时为错误信息
+    pub result: Result<XiaoaiResponse, String>,
 }
 
 fn default_match_mode() -> MatchMode {
@@ -39,6 +97,7 @@ fn default_enabled() -> bool {
 /// 匹配模式。
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
+#[non_exhaustive]
 pub enum MatchMode {
     /// 前缀匹配（推荐，准确度高）
     StartsWith,
@@ -46,6 +105,8 @@ pub enum MatchMode {
     Contains,
     /// 精确匹配
     Exact,
+    /// 正则表达式匹配，捕获组会放入 [`KeywordMatch::captures`]
+    Regex,
 }
 
 /// 关键词监听器配置。
@@ -74,6 +135,14 @@ pub struct WatcherConfig {
     /// 是否在检测到关键词后暂停小爱回复
     #[serde(default = "default_block_xiaoai")]
     pub block_xiaoai_response: bool,
+
+    /// 去重窗口（秒），默认为 0，只跳过时间戳相同的对话；大于 0 时窗口内同一句话再次命中也会跳过
+    #[serde(default = "default_dedup_window")]
+    pub dedup_window: f64,
+
+    /// 最多记住多少条最近的命中，用于去重
+    #[serde(default = "default_dedup_history")]
+    pub dedup_history: usize,
 }
 
 /// 自定义反序列化函数，支持字符串数组和配置对象数组两种格式
@@ -98,6 +167,7 @@ where
                             match_mode: MatchMode::StartsWith,
                             enabled: true,
                             description: String::new(),
+                            actions: Vec::new(),
                         })
                     } else {
                         None
@@ -110,7 +180,7 @@ where
         Value::Array(arr) => {
             let configs: Result<Vec<KeywordConfig>, _> = arr
                 .into_iter()
-                .map(|v| serde_json::from_value(v))
+                .map(serde_json::from_value)
                 .collect();
             configs.map_err(Error::custom)
         }
@@ -126,6 +196,8 @@ fn default_min_interval() -> f64 { 0.5 }
 fn default_max_interval() -> f64 { 3.0 }
 fn default_fetch_limit() -> u32 { 5 }
 fn default_block_xiaoai() -> bool { true }
+fn default_dedup_window() -> f64 { 0.0 }
+fn default_dedup_history() -> usize { 32 }
 
 impl Default for WatcherConfig {
     fn default() -> Self {
@@ -136,12 +208,15 @@ impl Default for WatcherConfig {
             max_interval: default_max_interval(),
             fetch_limit: default_fetch_limit(),
             block_xiaoai_response: default_block_xiaoai(),
+            dedup_window: default_dedup_window(),
+            dedup_history: default_dedup_history(),
         }
     }
 }
 
 /// 关键词匹配结果。
 #[derive(Clone, Debug)]
+#[non_exhaustive]
 pub struct KeywordMatch {
     /// 匹配到的关键词配置
     pub config: KeywordConfig,
@@ -149,35 +224,161 @@ pub struct KeywordMatch {
     pub matched_keyword: String,
     /// 触发的对话
     pub conversation: Conversation,
+    /// 正则表达式的捕获组（不含整个匹配），未参与匹配的组为 `None`；其他匹配模式下为空
+    pub captures: Vec<Option<String>>,
+    /// 配置中声明的动作的执行结果，按配置顺序排列
+    pub action_results: Vec<ActionResult>,
+}
+
+/// 监听结束时的统计。
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WatchStats {
+    /// 轮询次数
+    pub polls: u64,
+    /// 匹配到关键词的次数，不含被去重跳过的命中
+    pub matches: u64,
+    /// 在去重窗口内重复命中而被跳过的次数
+    pub duplicates: u64,
+}
+
+/// 配置中所有正则表达式匹配模式的关键词。
+fn regex_patterns(config: &WatcherConfig) -> impl Iterator<Item = &str> {
+    config
+        .keywords
+        .iter()
+        .filter(|kw| kw.match_mode == MatchMode::Regex)
+        .flat_map(|kw| kw.keywords.iter().map(String::as_str))
+}
+
+/// 心跳日志使用的 tracing target，便于单独开启。
+pub const HEARTBEAT_TARGET: &str = "miai::heartbeat";
+
+/// 被限流时轮询间隔的上限（秒），服务端要求的 `Retry-After` 也不会超过它。
+const MAX_RATE_LIMITED_INTERVAL: f64 = 60.0;
+
+/// 等待 `duration`，期间收到停止信号时提前返回，返回是否应当停止。
+async fn sleep_or_shutdown(duration: Duration, shutdown: &mut watch::Receiver<bool>) -> bool {
+    let signal = async {
+        // 发送端已丢弃时停止信号不会再到来，只等待 sleep
+        if shutdown.wait_for(|stop| *stop).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+    };
+
+    let stopped = tokio::select! {
+        _ = tokio::time::sleep(duration) => false,
+        _ = signal => true,
+    };
+    stopped || *shutdown.borrow()
+}
+
+/// 一次命中的记录，用于去重。
+#[derive(Debug)]
+struct RecentHit {
+    query: String,
+    /// 命中时的本地时间
+    at: Instant,
 }
 
 /// 小爱对话监听器。
 pub struct ConversationWatcher {
     config: WatcherConfig,
     seen_timestamps: HashSet<i64>,
+    /// 最近命中的对话，用于去重，最旧的在前
+    recent_hits: VecDeque<RecentHit>,
     current_interval: f64,
+    heartbeat: Option<Duration>,
+    /// 已编译的正则表达式，键为配置中的原始表达式
+    regexes: HashMap<String, Regex>,
 }
 
 impl ConversationWatcher {
     /// 创建新的监听器。
+    ///
+    /// 无效的正则表达式关键词会被忽略并输出警告，需要在加载时报错的话使用 [`ConversationWatcher::try_new`]。
     pub fn new(config: WatcherConfig) -> Self {
+        let regexes = regex_patterns(&config)
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some((pattern.to_string(), regex)),
+                Err(e) => {
+                    warn!("忽略无效的关键词正则表达式 {}: {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+        Self::with_regexes(config, regexes)
+    }
+
+    /// 创建新的监听器，并编译所有正则表达式关键词。
+    ///
+    /// # Errors
+    ///
+    /// 存在无效的正则表达式时，将返回 [`Error::InvalidRegex`][crate::Error::InvalidRegex]。
+    pub fn try_new(config: WatcherConfig) -> crate::Result<Self> {
+        let regexes = regex_patterns(&config)
+            .map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Ok((pattern.to_string(), regex)),
+                Err(source) => Err(crate::Error::InvalidRegex {
+                    pattern: pattern.to_string(),
+                    source,
+                }),
+            })
+            .collect::<crate::Result<_>>()?;
+        Ok(Self::with_regexes(config, regexes))
+    }
+
+    fn with_regexes(config: WatcherConfig, regexes: HashMap<String, Regex>) -> Self {
         Self {
             current_interval: config.initial_interval,
             config,
             seen_timestamps: HashSet::new(),
+            recent_hits: VecDeque::new(),
+            heartbeat: None,
+            regexes,
         }
     }
 
+    /// 设置心跳间隔，`None` 表示关闭（默认）。
+    ///
+    /// 开启后，监听循环每隔一段时间会以 [`HEARTBEAT_TARGET`] 为 target 输出一条 `info` 日志，
+    /// 包含轮询次数和上次成功轮询的时间，用于在长时间没有匹配时确认监听仍在运行。
+    pub fn set_heartbeat(&mut self, interval: Option<Duration>) {
+        self.heartbeat = interval;
+    }
+
     /// 从 JSON 文件加载配置。
+    ///
+    /// # Errors
+    ///
+    /// 加载失败时返回 [`Error::ConfigFile`][crate::Error::ConfigFile]，其中带有文件路径。
     pub fn from_json_file(path: impl AsRef<std::path::Path>) -> crate::Result<Self> {
         let path = path.as_ref();
-        let content = std::fs::read_to_string(path)
+        std::fs::File::open(path)
             .map_err(|e| {
                 // 将 IO 错误转换为 serde_json 错误
-                serde_json::Error::io(e)
-            })?;
-        let config: WatcherConfig = serde_json::from_str(&content)?;
-        Ok(Self::new(config))
+                serde_json::Error::io(e).into()
+            })
+            .and_then(|file| Self::from_reader(std::io::BufReader::new(file)))
+            .map_err(|e| crate::Error::ConfigFile {
+                path: path.to_path_buf(),
+                source: Box::new(e),
+            })
+    }
+
+    /// 从任意来源读取 JSON 配置。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use miai::ConversationWatcher;
+    ///
+    /// let config = r#"{"keywords": ["请问", "请帮我"]}"#;
+    /// let watcher = ConversationWatcher::from_reader(config.as_bytes()).unwrap();
+    /// assert_eq!(watcher.get_enabled_keywords().collect::<Vec<_>>(), ["请问", "请帮我"]);
+    /// ```
+    pub fn from_reader<R: std::io::Read>(reader: R) -> crate::Result<Self> {
+        let config: WatcherConfig = serde_json::from_reader(reader)?;
+        Self::try_new(config)
     }
 
     /// 获取所有已启用的关键词列表（用于显示）。
@@ -203,8 +404,32 @@ impl ConversationWatcher {
         xiaoai: &Xiaoai,
         device_id: &str,
         hardware: &str,
-        mut on_match: F,
+        on_match: F,
     ) -> crate::Result<()>
+    where
+        F: FnMut(KeywordMatch) -> Fut,
+        Fut: std::future::Future<Output = crate::Result<()>>,
+    {
+        // 发送端立即丢弃，停止信号永远不会到来
+        let (_, shutdown) = watch::channel(false);
+        self.watch_until(xiaoai, device_id, hardware, shutdown, on_match)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 同 [`ConversationWatcher::watch`]，但可以通过 `shutdown` 通知循环停止。
+    ///
+    /// 向 `shutdown` 发送 `true` 后，循环会在当前轮询和回调处理完后退出，正在等待下一次轮询时立即退出，
+    /// 不会像 `abort` 那样在回调执行到一半时中断。退出时返回轮询的统计。
+    pub async fn watch_until<F, Fut>(
+        &mut self,
+        xiaoai: &Xiaoai,
+        device_id: &str,
+        hardware: &str,
+        mut shutdown: watch::Receiver<bool>,
+        mut on_match: F,
+    ) -> crate::Result<WatchStats>
     where
         F: FnMut(KeywordMatch) -> Fut,
         Fut: std::future::Future<Output = crate::Result<()>>,
@@ -234,11 +459,54 @@ impl ConversationWatcher {
               self.config.max_interval);
         info!("按 Ctrl+C 停止监听\n");
 
+        let mut stats = WatchStats::default();
+        let mut last_success: Option<Instant> = None;
+        let mut last_heartbeat = Instant::now();
+
         loop {
+            // 心跳
+            if let Some(interval) = self.heartbeat {
+                if last_heartbeat.elapsed() >= interval {
+                    let last_success = last_success
+                        .map(|t| format!("{}s 前", t.elapsed().as_secs()))
+                        .unwrap_or_else(|| "无".to_string());
+                    info!(
+                        target: HEARTBEAT_TARGET,
+                        "仍在监听设备 {}，已轮询 {} 次，上次成功: {}",
+                        device_id, stats.polls, last_success
+                    );
+                    last_heartbeat = Instant::now();
+                }
+            }
+
             // 拉取最新对话
-            let conversations = xiaoai
+            stats.polls += 1;
+            let conversations = match xiaoai
                 .get_conversations(device_id, hardware, Some(self.config.fetch_limit))
-                .await?;
+                .await
+            {
+                Ok(conversations) => conversations,
+                Err(crate::Error::RateLimited { retry_after }) => {
+                    // 被限流时加倍轮询间隔，不再受 max_interval 限制，直到恢复正常
+                    self.current_interval = (self.current_interval * 2.0)
+                        .max(self.config.max_interval)
+                        .min(MAX_RATE_LIMITED_INTERVAL);
+                    let wait = retry_after.map_or(self.current_interval, |retry_after| {
+                        retry_after
+                            .as_secs_f64()
+                            .min(MAX_RATE_LIMITED_INTERVAL)
+                            .max(self.current_interval)
+                    });
+                    warn!("请求过于频繁，{:.1}s 后再次轮询", wait);
+                    if sleep_or_shutdown(Duration::from_secs_f64(wait), &mut shutdown).await {
+                        info!("收到停止信号，共轮询 {} 次，匹配 {} 次", stats.polls, stats.matches);
+                        return Ok(stats);
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            last_success = Some(Instant::now());
 
             // 过滤出新对话
             let new_conversations: Vec<_> = conversations
@@ -257,7 +525,13 @@ impl ConversationWatcher {
                     self.seen_timestamps.insert(conv.time);
                     
                     // 检查是否匹配关键词
-                    if let Some(keyword_match) = self.match_keywords(conv) {
+                    if let Some(mut keyword_match) = self.match_keywords(conv) {
+                        if self.is_duplicate_hit(conv) {
+                            debug!("去重窗口内重复命中，跳过: {}", conv.query);
+                            stats.duplicates += 1;
+                            continue;
+                        }
+
                         info!("🔥 检测到关键词触发！");
                         info!("  查询: {}", conv.query);
                         info!("  匹配: {} ({})", 
@@ -271,8 +545,22 @@ impl ConversationWatcher {
                                 warn!("暂停小爱回复失败: {}", e);
                             }
                         }
+
+                        // 执行配置中的动作，失败不影响后续动作
+                        for action in &keyword_match.config.actions {
+                            let result = action.run(xiaoai, device_id).await;
+                            match &result {
+                                Ok(_) => info!("  执行动作: {:?}", action.kind),
+                                Err(e) => warn!("执行动作 {:?} 失败: {}", action.kind, e),
+                            }
+                            keyword_match.action_results.push(ActionResult {
+                                action: action.clone(),
+                                result: result.map_err(|e| e.to_string()),
+                            });
+                        }
                         
                         // 调用用户回调
+                        stats.matches += 1;
                         on_match(keyword_match).await?;
                     } else {
                         trace!("对话未匹配关键词: {}", conv.query);
@@ -285,10 +573,47 @@ impl ConversationWatcher {
             }
 
             // 等待下一次轮询
-            tokio::time::sleep(Duration::from_secs_f64(self.current_interval)).await;
+            if sleep_or_shutdown(Duration::from_secs_f64(self.current_interval), &mut shutdown).await {
+                info!("收到停止信号，共轮询 {} 次，匹配 {} 次", stats.polls, stats.matches);
+                return Ok(stats);
+            }
         }
     }
 
+    /// 检查命中是否与最近的命中重复，不重复时记录下来。
+    ///
+    /// 在 [`WatcherConfig::dedup_window`] 内出现过同一句话时视为重复，窗口为 0 时不去重；
+    /// 时间戳相同的对话在拉取时就已经跳过，不会走到这里。最多记住 [`WatcherConfig::dedup_history`] 条命中。
+    fn is_duplicate_hit(&mut self, conversation: &Conversation) -> bool {
+        // 配置中的窗口可能是负数或超出 Duration 的范围
+        let window = Duration::try_from_secs_f64(self.config.dedup_window.max(0.0))
+            .unwrap_or(Duration::MAX);
+        if window.is_zero() {
+            return false;
+        }
+
+        let query = conversation.query.trim();
+        let duplicate = self
+            .recent_hits
+            .iter()
+            .any(|hit| hit.query == query && hit.at.elapsed() < window);
+        if duplicate {
+            return true;
+        }
+
+        if self.config.dedup_history > 0 {
+            while self.recent_hits.len() >= self.config.dedup_history {
+                self.recent_hits.pop_front();
+            }
+            self.recent_hits.push_back(RecentHit {
+                query: query.to_string(),
+                at: Instant::now(),
+            });
+        }
+
+        false
+    }
+
     /// 匹配关键词。
     fn match_keywords(&self, conversation: &Conversation) -> Option<KeywordMatch> {
         let query = conversation.query.as_str();
@@ -299,17 +624,29 @@ impl ConversationWatcher {
             }
             
             for keyword in &config.keywords {
-                let matched = match config.match_mode {
-                    MatchMode::StartsWith => query.starts_with(keyword),
-                    MatchMode::Contains => query.contains(keyword),
-                    MatchMode::Exact => query == keyword,
+                let captures = match config.match_mode {
+                    MatchMode::StartsWith => query.starts_with(keyword).then(Vec::new),
+                    MatchMode::Contains => query.contains(keyword).then(Vec::new),
+                    MatchMode::Exact => (query == keyword).then(Vec::new),
+                    MatchMode::Regex => self
+                        .regexes
+                        .get(keyword)
+                        .and_then(|regex| regex.captures(query))
+                        .map(|caps| {
+                            caps.iter()
+                                .skip(1)
+                                .map(|group| group.map(|m| m.as_str().to_string()))
+                                .collect()
+                        }),
                 };
                 
-                if matched {
+                if let Some(captures) = captures {
                     return Some(KeywordMatch {
                         config: config.clone(),
                         matched_keyword: keyword.clone(),
                         conversation: conversation.clone(),
+                        captures,
+                        action_results: Vec::new(),
                     });
                 }
             }
@@ -328,3 +665,120 @@ impl ConversationWatcher {
         self.seen_timestamps.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watcher(dedup_window: f64, dedup_history: usize) -> ConversationWatcher {
+        ConversationWatcher::new(WatcherConfig {
+            dedup_window,
+            dedup_history,
+            ..WatcherConfig::default()
+        })
+    }
+
+    fn conversation(time: i64, query: &str) -> Conversation {
+        Conversation {
+            time,
+            query: query.to_string(),
+            answers: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn dedup_within_window() {
+        let mut watcher = watcher(10.0, 32);
+
+        assert!(!watcher.is_duplicate_hit(&conversation(1, "打开灯")));
+        assert!(watcher.is_duplicate_hit(&conversation(2, " 打开灯 ")));
+        assert!(!watcher.is_duplicate_hit(&conversation(3, "关闭灯")));
+    }
+
+    #[test]
+    fn dedup_window_expires() {
+        let mut watcher = watcher(0.01, 32);
+
+        assert!(!watcher.is_duplicate_hit(&conversation(1, "打开灯")));
+        std::thread::sleep(Duration::from_millis(20));
+        assert!(!watcher.is_duplicate_hit(&conversation(2, "打开灯")));
+    }
+
+    #[test]
+    fn dedup_disabled_by_default() {
+        let mut watcher = ConversationWatcher::new(WatcherConfig::default());
+
+        assert!(!watcher.is_duplicate_hit(&conversation(1, "打开灯")));
+        assert!(!watcher.is_duplicate_hit(&conversation(2, "打开灯")));
+    }
+
+    #[test]
+    fn dedup_history_evicts_oldest() {
+        let mut watcher = watcher(10.0, 2);
+
+        assert!(!watcher.is_duplicate_hit(&conversation(1, "一")));
+        assert!(!watcher.is_duplicate_hit(&conversation(2, "二")));
+        assert!(!watcher.is_duplicate_hit(&conversation(3, "三")));
+        // “一”已经被挤出，“三”还在
+        assert!(!watcher.is_duplicate_hit(&conversation(4, "一")));
+        assert!(watcher.is_duplicate_hit(&conversation(5, "三")));
+    }
+
+    #[test]
+    fn dedup_window_out_of_range() {
+        for dedup_window in [1e20, f64::INFINITY] {
+            let mut watcher = watcher(dedup_window, 32);
+            assert!(!watcher.is_duplicate_hit(&conversation(1, "打开灯")));
+            assert!(watcher.is_duplicate_hit(&conversation(2, "打开灯")));
+        }
+        for dedup_window in [-1.0, f64::NAN] {
+            let mut watcher = watcher(dedup_window, 32);
+            assert!(!watcher.is_duplicate_hit(&conversation(1, "打开灯")));
+            assert!(!watcher.is_duplicate_hit(&conversation(2, "打开灯")));
+        }
+    }
+
+    #[test]
+    fn invalid_regex_fails_to_load() {
+        let config = r#"{"keywords": [{"keywords": ["播放(.+"], "match_mode": "regex"}]}"#;
+
+        let error = ConversationWatcher::from_reader(config.as_bytes()).err().unwrap();
+        assert!(matches!(
+            error,
+            crate::Error::InvalidRegex { ref pattern, .. } if pattern == "播放(.+"
+        ));
+
+        let path = std::env::temp_dir()
+            .join(format!("miai-invalid-regex-{}.json", std::process::id()));
+        std::fs::write(&path, config).unwrap();
+        let error = ConversationWatcher::from_json_file(&path).err().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let crate::Error::ConfigFile { source, .. } = error else {
+            panic!("应为 ConfigFile 错误: {:?}", error);
+        };
+        assert!(matches!(*source, crate::Error::InvalidRegex { .. }));
+    }
+
+    #[test]
+    fn regex_keyword_captures() {
+        let config = r#"{"keywords": [
+            {"keywords": ["^播放(.+?)(的歌)?$"], "match_mode": "regex"},
+            {"keywords": ["关灯"]}
+        ]}"#;
+        let watcher = ConversationWatcher::from_reader(config.as_bytes()).unwrap();
+
+        let keyword_match = watcher.match_keywords(&conversation(1, "播放周杰伦的歌")).unwrap();
+        assert_eq!(keyword_match.matched_keyword, "^播放(.+?)(的歌)?$");
+        assert_eq!(
+            keyword_match.captures,
+            [Some("周杰伦".to_string()), Some("的歌".to_string())]
+        );
+
+        let keyword_match = watcher.match_keywords(&conversation(2, "播放晴天")).unwrap();
+        assert_eq!(keyword_match.captures, [Some("晴天".to_string()), None]);
+
+        let keyword_match = watcher.match_keywords(&conversation(3, "关灯吧")).unwrap();
+        assert!(keyword_match.captures.is_empty());
+        assert!(watcher.match_keywords(&conversation(4, "开灯")).is_none());
+    }
+}
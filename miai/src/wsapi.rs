@@ -0,0 +1,229 @@
+//! WebSocket API 的协议类型。
+//!
+//! 命令行的 `wsapi` 服务器使用这里的类型收发 JSON 文本消息，其他 Rust 程序可以直接依赖这些类型，
+//! 而不必重新声明一遍协议。协议的详细说明见仓库中的 `API.md`。
+//!
+//! # 示例
+//!
+//! ```
+//! use miai::wsapi::ApiRequest;
+//!
+//! let request = ApiRequest::say("123456", "你好");
+//! let text = serde_json::to_string(&request).unwrap();
+//! assert_eq!(ApiRequest::try_from(text.as_str()).unwrap(), request);
+//!
+//! let request = ApiRequest::try_from(r#"{"command":"get_devices"}"#).unwrap();
+//! assert_eq!(request, ApiRequest::GetDevices);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// WebSocket API 请求。
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ApiRequest {
+    Say {
+        device_id: String,
+        text: String,
+    },
+    Play {
+        device_id: String,
+        url: Option<String>,
+    },
+    Pause {
+        device_id: String,
+    },
+    Stop {
+        device_id: String,
+    },
+    Volume {
+        device_id: String,
+        volume: u32,
+    },
+    Ask {
+        device_id: String,
+        text: String,
+    },
+    Status {
+        device_id: String,
+    },
+    GetDevices,
+    /// 获取设备的在线状态、固件版本、音量和播放状态
+    GetDeviceStatus {
+        device_id: String,
+    },
+    /// 获取服务器支持的命令和版本
+    Describe,
+    /// 订阅设备的播放器状态，服务器每隔 `interval_secs` 秒推送一次 [`ApiResponse::StatusUpdate`]
+    ///
+    /// 重复订阅同一台设备时只会更新推送间隔，断开连接后订阅自动取消。
+    Subscribe {
+        device_id: String,
+        #[serde(default = "default_interval_secs")]
+        interval_secs: u64,
+    },
+    /// 取消订阅
+    Unsubscribe {
+        device_id: String,
+    },
+    /// 服务器开启鉴权时，连接后发送的第一条消息
+    Auth {
+        token: String,
+    },
+}
+
+impl ApiRequest {
+    /// 播报文字。
+    pub fn say(device_id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::Say {
+            device_id: device_id.into(),
+            text: text.into(),
+        }
+    }
+
+    /// 播放 `url`，为 `None` 时继续播放。
+    pub fn play(device_id: impl Into<String>, url: Option<String>) -> Self {
+        Self::Play {
+            device_id: device_id.into(),
+            url,
+        }
+    }
+
+    /// 暂停播放。
+    pub fn pause(device_id: impl Into<String>) -> Self {
+        Self::Pause {
+            device_id: device_id.into(),
+        }
+    }
+
+    /// 停止播放。
+    pub fn stop(device_id: impl Into<String>) -> Self {
+        Self::Stop {
+            device_id: device_id.into(),
+        }
+    }
+
+    /// 设置音量。
+    pub fn volume(device_id: impl Into<String>, volume: u32) -> Self {
+        Self::Volume {
+            device_id: device_id.into(),
+            volume,
+        }
+    }
+
+    /// 询问小爱。
+    pub fn ask(device_id: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::Ask {
+            device_id: device_id.into(),
+            text: text.into(),
+        }
+    }
+
+    /// 获取播放器状态。
+    pub fn status(device_id: impl Into<String>) -> Self {
+        Self::Status {
+            device_id: device_id.into(),
+        }
+    }
+
+    /// 获取设备状态。
+    pub fn device_status(device_id: impl Into<String>) -> Self {
+        Self::GetDeviceStatus {
+            device_id: device_id.into(),
+        }
+    }
+
+    /// 订阅播放器状态。
+    pub fn subscribe(device_id: impl Into<String>, interval_secs: u64) -> Self {
+        Self::Subscribe {
+            device_id: device_id.into(),
+            interval_secs,
+        }
+    }
+
+    /// 取消订阅。
+    pub fn unsubscribe(device_id: impl Into<String>) -> Self {
+        Self::Unsubscribe {
+            device_id: device_id.into(),
+        }
+    }
+
+    /// 鉴权。
+    pub fn auth(token: impl Into<String>) -> Self {
+        Self::Auth {
+            token: token.into(),
+        }
+    }
+}
+
+/// [`ApiRequest::Subscribe`] 默认的推送间隔（秒）
+pub const DEFAULT_SUBSCRIBE_INTERVAL_SECS: u64 = 5;
+
+fn default_interval_secs() -> u64 {
+    DEFAULT_SUBSCRIBE_INTERVAL_SECS
+}
+
+/// 从一条 JSON 文本消息解析请求。
+impl TryFrom<&str> for ApiRequest {
+    type Error = serde_json::Error;
+
+    fn try_from(text: &str) -> Result<Self, Self::Error> {
+        serde_json::from_str(text)
+    }
+}
+
+/// WebSocket API 响应。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum ApiResponse {
+    Success {
+        code: i64,
+        message: String,
+        data: Value,
+        /// 底层请求的耗时（毫秒）
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        elapsed_ms: Option<u64>,
+    },
+    Error {
+        error: String,
+    },
+    /// 鉴权失败，服务器随后会关闭连接
+    Unauthorized {
+        error: String,
+    },
+    Devices {
+        devices: Vec<DeviceData>,
+    },
+    /// [`ApiRequest::Describe`] 的响应
+    Capabilities {
+        /// 服务器的版本
+        version: String,
+        /// 支持的命令，即请求中 `command` 字段的取值
+        commands: Vec<String>,
+    },
+    /// 订阅的设备的播放器状态，`status` 和 `status` 命令返回的 `data` 相同
+    StatusUpdate {
+        device_id: String,
+        status: Value,
+    },
+    KeywordMatch {
+        timestamp: i64,
+        query: String,
+        matched_keyword: String,
+        device_id: String,
+        /// 正则表达式关键词的捕获组
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        captures: Vec<Option<String>>,
+    },
+}
+
+/// [`ApiResponse::Devices`] 中的设备信息。
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeviceData {
+    pub device_id: String,
+    pub name: String,
+    pub hardware: String,
+}
@@ -1,20 +1,69 @@
 use std::{
     collections::HashMap,
     io::{BufRead, Write},
-    sync::Arc,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
 };
 
-use cookie_store::serde::json::{load_all, save_incl_expired_and_nonpersistent};
-use reqwest::{Client, Url};
+use cookie_store::{
+    CookieExpiration, CookieStore, RawCookie,
+    serde::json::{load_all, save, save_incl_expired_and_nonpersistent},
+};
+use reqwest::{Client, Proxy, RequestBuilder, StatusCode, Url};
 use reqwest_cookie_store::CookieStoreMutex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use tracing::trace;
+use ::time::OffsetDateTime;
+use tokio::time::sleep;
+use tracing::{Instrument, Span, debug, debug_span, field::Empty, instrument, trace, warn};
+
+use crate::{
+    XiaoaiResponse,
+    alarm::{self, AlarmSpec},
+    client::ClientConfig,
+    dnd,
+    login::Login,
+    model::{DEFAULT_MODEL, DEFAULT_PLAY_URL_TYPE, ModelInfo, PlayMethod, VOLUME_RANGE, model_info},
+    radio::find_station,
+    sleep_timer,
+    source::{self, Source},
+    time::Time,
+    ubus::UbusMessage,
+    util::random_request_id,
+};
+
+/// [`Xiaoai::ask`] 等待小爱回答时的轮询间隔。
+const ASK_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// [`Xiaoai::ask`] 等待小爱回答的最大轮询次数。
+const ASK_POLL_ATTEMPTS: u32 = 10;
+
+/// 会触发播放的意图域。
+const PLAYBACK_DOMAINS: &[&str] = &["music", "audiobook", "radio", "station", "news", "joke"];
+
+/// [`Xiaoai::set_play_state_confirmed`] 发出操作后，读取状态前的等待时间。
+const CONFIRM_DELAY: Duration = Duration::from_millis(500);
+
+/// 第一次重试前的等待时间。
+const RETRY_BACKOFF: Duration = Duration::from_millis(500);
 
-use crate::{XiaoaiResponse, login::Login, util::random_id};
+/// 被限流且服务端没有给出 `Retry-After` 时，重试前的等待时间。
+const RATE_LIMIT_BACKOFF: Duration = Duration::from_secs(5);
 
-const API_SERVER: &str = "https://api2.mina.mi.com/";
-const API_UA: &str = "MiHome/6.0.103 (com.xiaomi.mihome; build:6.0.103.1; iOS 14.4.0) Alamofire/6.0.103 MICO/iOSApp/appStore/6.0.103";
+/// 响应中表示限流的 `code`，与 HTTP 的 429 状态码相同。
+const RATE_LIMITED_CODE: i64 = 429;
+
+/// 被限流后重试前最多等待的时间，服务端要求等待更久时直接返回错误。
+const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
+
+/// 自动重新登录失败后，在这段时间内不再尝试，避免频繁请求登录接口。
+const RELOGIN_COOLDOWN: Duration = Duration::from_secs(60);
+
+/// 缓存的设备列表和获取时间。
+type DeviceCache = Arc<RwLock<Option<(Instant, Vec<DeviceInfo>)>>>;
 
 /// 提供小爱服务请求。
 ///
@@ -24,43 +73,328 @@ const API_UA: &str = "MiHome/6.0.103 (com.xiaomi.mihome; build:6.0.103.1; iOS 14
 pub struct Xiaoai {
     client: Client,
     cookie_store: Arc<CookieStoreMutex>,
-    server: Url,
+    config: ClientConfig,
+    device_cache: DeviceCache,
+    relogin: Option<Arc<Relogin>>,
+    on_relogin: Option<OnRelogin>,
+}
+
+/// [`Xiaoai::on_relogin`] 设置的回调。
+#[derive(Clone)]
+struct OnRelogin(Arc<dyn Fn(&Xiaoai) + Send + Sync>);
+
+impl std::fmt::Debug for OnRelogin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OnRelogin(..)")
+    }
+}
+
+/// [`Xiaoai::with_relogin_credentials`] 设置的账号密码和重新登录的状态。
+struct Relogin {
+    username: String,
+    password: String,
+    /// 成功重新登录的次数，用于判断等待期间是否已经有其他请求完成了重新登录
+    generation: AtomicU64,
+    /// 保证同一时间只有一个请求在重新登录，锁内记录上次失败的时间
+    last_failure: tokio::sync::Mutex<Option<Instant>>,
+}
+
+/// 不输出密码。
+impl std::fmt::Debug for Relogin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Relogin")
+            .field("username", &self.username)
+            .field("generation", &self.generation)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Xiaoai {
     /// 登录以调用小爱服务。
     pub async fn login(username: &str, password: &str) -> crate::Result<Self> {
         let login = Login::new(username, password)?;
-        let login_response = login.login().await?;
-        let auth_response = login.auth(login_response).await?;
-        login.get_token(auth_response).await?;
+        complete_login(&login).await?;
 
         Self::from_login(login)
     }
 
     /// 从 [`Login`][`crate::login::Login`] 构造。
+    ///
+    /// 这里会另外构造一个 [`Client`]，与登录时的 `Client` 共享同一个 Cookies。
+    /// 两者的 UA 和重定向策略都不同，而 [`Client`] 构造后无法修改这些设置；
+    /// 相比登录所需的三次网络请求，这次构造的开销可以忽略。
     pub fn from_login(login: Login) -> crate::Result<Self> {
-        let cookie_store = login.into_cookie_store();
-        let client = Client::builder()
-            .user_agent(API_UA)
-            .cookie_provider(cookie_store.clone())
-            .build()?;
+        let (cookie_store, config) = login.into_parts();
+
+        Self::from_cookie_store(cookie_store, config)
+    }
+
+    /// 从已有的 Cookie 字符串构造，不再经过账号密码登录。
+    ///
+    /// `cookies` 形如 `userId=xxx; serviceToken=yyy`，可以从浏览器或其他工具中获取。
+    /// `server` 为 API 服务器，`None` 时使用默认服务器，Cookie 会添加到该服务器下。
+    ///
+    /// # Errors
+    ///
+    /// 缺少 `userId` 或 `serviceToken` 时，将返回 [`Error::MissingCookie`][crate::Error::MissingCookie]。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use miai::Xiaoai;
+    ///
+    /// let xiaoai = Xiaoai::from_cookies("userId=123; serviceToken=abc", None).unwrap();
+    /// assert_eq!(xiaoai.user_id().as_deref(), Some("123"));
+    /// assert!(Xiaoai::from_cookies("userId=123", None).is_err());
+    /// ```
+    pub fn from_cookies(cookies: &str, server: Option<Url>) -> crate::Result<Self> {
+        let mut config = ClientConfig::default();
+        if let Some(server) = server {
+            config.api_server = server;
+        }
+
+        let mut cookie_store = CookieStore::new(None);
+        for (name, value) in cookies
+            .split(';')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(name, value)| (name.trim(), value.trim()))
+            .filter(|(name, _)| !name.is_empty())
+        {
+            let cookie = RawCookie::build((name, value)).path("/").build();
+            cookie_store.insert_raw(&cookie, &config.api_server)?;
+        }
+
+        for name in ["userId", "serviceToken"] {
+            if !cookie_store
+                .iter_unexpired()
+                .any(|cookie| cookie.name() == name)
+            {
+                return Err(crate::Error::MissingCookie(name));
+            }
+        }
+
+        Self::from_cookie_store(Arc::new(CookieStoreMutex::new(cookie_store)), config)
+    }
+
+    /// 是否跳过 TLS 证书校验，默认不跳过。
+    ///
+    /// **危险**：仅用于通过 mitmproxy 等代理调试接口，跳过校验后连接可以被任意中间人窃听和篡改。
+    pub fn danger_accept_invalid_certs(mut self, accept: bool) -> crate::Result<Self> {
+        self.config.accept_invalid_certs = accept;
+
+        self.rebuild_client()
+    }
+
+    /// 通过代理请求小爱服务。
+    ///
+    /// 不设置时会读取 `HTTP_PROXY`、`HTTPS_PROXY` 等环境变量。
+    pub fn with_proxy(mut self, proxy: Proxy) -> crate::Result<Self> {
+        self.config.proxy = Some(proxy);
+
+        self.rebuild_client()
+    }
+
+    /// 设置模拟的米家 APP 版本，默认为 [`DEFAULT_APP_VERSION`][crate::DEFAULT_APP_VERSION]。
+    ///
+    /// 版本会写入 User-Agent，旧版本被服务端弃用导致请求失败时，可以先用此方法换成较新的版本。
+    pub fn app_version(mut self, version: impl Into<String>) -> crate::Result<Self> {
+        self.config.app_version = version.into();
+
+        self.rebuild_client()
+    }
+
+    /// 使用自定义的 API 服务器，默认为 `https://api2.mina.mi.com/`。
+    ///
+    /// 用于其他区域的服务器，`server` 应以 `/` 结尾，请求路径会与它做 [`Url::join`]。
+    /// 登录状态中需要有该服务器可用的 Cookies。
+    pub fn with_server(mut self, server: Url) -> Self {
+        self.config.api_server = server;
+        self
+    }
+
+    /// 设置请求的超时时间，默认为 [`DEFAULT_TIMEOUT`][crate::DEFAULT_TIMEOUT]。
+    pub fn timeout(mut self, timeout: Duration) -> crate::Result<Self> {
+        self.config.timeout = timeout;
+
+        self.rebuild_client()
+    }
+
+    /// 设置 GET 请求失败后的最大重试次数，默认为 0，即不重试。
+    ///
+    /// 只有超时、连接失败和服务端 5xx 错误会重试，每次重试前的等待时间翻倍。
+    /// POST 请求（如播报、播放）不会重试，以免重复执行。
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.config.max_retries = max_retries;
+        self
+    }
+
+    /// 被限流时是否等待后重试一次，默认不重试，直接返回 [`Error::RateLimited`][crate::Error::RateLimited]。
+    ///
+    /// 等待时间优先使用服务端的 `Retry-After`，没有时等待 5 秒；服务端要求等待超过 60 秒时不重试。
+    /// 被限流的请求没有被执行，因此 POST 请求也会重试。
+    pub fn retry_on_rate_limit(mut self, retry: bool) -> Self {
+        self.config.retry_on_rate_limit = retry;
+        self
+    }
+
+    /// 登录状态失效时，用 `username` 和 `password` 自动重新登录一次并重放失败的请求，默认关闭。
+    ///
+    /// 适合长时间运行的服务。同一时间只会有一个请求在重新登录，其他同时失效的请求会等待它完成后直接重放；
+    /// 重新登录失败（如需要验证码）后的一段时间内不再尝试，直接返回
+    /// [`Error::SessionExpired`][crate::Error::SessionExpired]。
+    ///
+    /// 重新登录使用默认的登录服务器，以及当前的代理、超时等设置。新的登录状态只保存在内存中，
+    /// 需要持久化时用 [`Xiaoai::on_relogin`] 在重新登录成功后保存。
+    ///
+    /// **注意**：开启后密码会以明文保存在内存中。请求体无法复制（如流式上传）的请求不会重放。
+    pub fn with_relogin_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.relogin = Some(Arc::new(Relogin {
+            username: username.into(),
+            password: password.into(),
+            generation: AtomicU64::new(0),
+            last_failure: tokio::sync::Mutex::new(None),
+        }));
+        self
+    }
+
+    /// 自动重新登录成功后调用 `callback`，参数为换入了新 Cookies 的 `Xiaoai`。
+    ///
+    /// 通常用于把新的登录状态 [`save`][Xiaoai::save] 回认证文件，避免重启后再次失效。
+    /// 回调在重新登录的锁内同步执行，不要在其中做耗时的操作。
+    pub fn on_relogin(mut self, callback: impl Fn(&Xiaoai) + Send + Sync + 'static) -> Self {
+        self.on_relogin = Some(OnRelogin(Arc::new(callback)));
+        self
+    }
+
+    fn from_cookie_store(
+        cookie_store: Arc<CookieStoreMutex>,
+        config: ClientConfig,
+    ) -> crate::Result<Self> {
+        let client = config.api_client(&cookie_store)?;
 
         Ok(Self {
             client,
             cookie_store,
-            server: Url::parse(API_SERVER)?,
+            config,
+            device_cache: Arc::default(),
+            relogin: None,
+            on_relogin: None,
         })
     }
 
+    /// 修改 `config` 后重新构造 `Client`，其余状态保持不变。
+    fn rebuild_client(mut self) -> crate::Result<Self> {
+        self.client = self.config.api_client(&self.cookie_store)?;
+        Ok(self)
+    }
+
     /// 列出所有设备的信息。
+    ///
+    /// 等同于 `device_info_with(false)`。
     pub async fn device_info(&self) -> crate::Result<Vec<DeviceInfo>> {
-        self.raw_device_info().await?.extract_data()
+        self.device_info_with(false).await
+    }
+
+    /// 同 [`Xiaoai::device_info`]，但在 `ttl` 内重复调用时直接返回缓存的设备列表。
+    ///
+    /// 缓存在克隆出的 `Xiaoai` 之间共享，设备变动后可以用 [`Xiaoai::invalidate_device_cache`] 让缓存失效。
+    pub async fn device_info_cached(&self, ttl: Duration) -> crate::Result<Vec<DeviceInfo>> {
+        if let Some((fetched_at, devices)) = &*self.device_cache.read().unwrap() {
+            if fetched_at.elapsed() < ttl {
+                return Ok(devices.clone());
+            }
+        }
+
+        let devices = self.device_info().await?;
+        *self.device_cache.write().unwrap() = Some((Instant::now(), devices.clone()));
+
+        Ok(devices)
+    }
+
+    /// 获取设备的在线状态、固件版本、音量和播放状态。
+    ///
+    /// 在线状态和固件版本取自设备列表，设备不是明确离线时再读取一次播放器状态。
+    /// 读取播放器状态失败不会报错，相应字段为 `None`。
+    ///
+    /// # Errors
+    ///
+    /// 设备列表中没有该设备时，将返回 [`Error::DeviceNotFound`][crate::Error::DeviceNotFound]。
+    pub async fn device_status(&self, device_id: &str) -> crate::Result<DeviceStatus> {
+        let info = self
+            .device_info()
+            .await?
+            .into_iter()
+            .find(|device| device.device_id == device_id)
+            .ok_or_else(|| crate::Error::DeviceNotFound(device_id.to_string()))?;
+
+        let online = info.is_online();
+        // 在线状态未知时也尝试读取，失败只会让播放相关的字段为 None
+        let player = if online != Some(false) {
+            match self.player_status_parsed(device_id).await {
+                Ok(status) => Some(status),
+                Err(e) => {
+                    warn!("读取设备 {} 的播放器状态失败: {}", device_id, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        Ok(DeviceStatus {
+            online,
+            firmware: info.rom_version.clone(),
+            volume: player.as_ref().and_then(PlayerStatus::volume),
+            play_state: player.and_then(|status| status.status),
+            info,
+        })
+    }
+
+    /// 清除 [`Xiaoai::device_info_cached`] 的缓存。
+    pub fn invalidate_device_cache(&self) {
+        *self.device_cache.write().unwrap() = None;
     }
 
     /// 同 [`Xiaoai::device_info`]，但返回原始的响应。
     pub async fn raw_device_info(&self) -> crate::Result<XiaoaiResponse> {
-        let response = self.get("admin/v2/device_list?master=0").await?;
+        self.raw_device_info_with(false).await
+    }
+
+    /// 列出设备的信息，`master` 对应设备列表接口的 `master` 参数。
+    ///
+    /// `master` 为 `true` 时会返回更完整的设备拓扑，如分组信息，参见 [`DeviceInfo::group`]。
+    pub async fn device_info_with(&self, master: bool) -> crate::Result<Vec<DeviceInfo>> {
+        self.raw_device_info_with(master).await?.extract_data()
+    }
+
+    /// 按名称查找设备，名称比较时不区分大小写。
+    ///
+    /// # Errors
+    ///
+    /// 找不到设备时返回 [`Error::DeviceNotFound`][crate::Error::DeviceNotFound]，
+    /// 有多个同名设备时返回 [`Error::AmbiguousDevice`][crate::Error::AmbiguousDevice]。
+    pub async fn device_by_name(&self, name: &str) -> crate::Result<DeviceInfo> {
+        let name_lower = name.to_lowercase();
+        let mut matched = self
+            .device_info()
+            .await?
+            .into_iter()
+            .filter(|device| device.name.to_lowercase() == name_lower);
+
+        match (matched.next(), matched.next()) {
+            (Some(device), None) => Ok(device),
+            (Some(_), Some(_)) => Err(crate::Error::AmbiguousDevice(name.to_string())),
+            (None, _) => Err(crate::Error::DeviceNotFound(name.to_string())),
+        }
+    }
+
+    /// 同 [`Xiaoai::device_info_with`]，但返回原始的响应。
+    pub async fn raw_device_info_with(&self, master: bool) -> crate::Result<XiaoaiResponse> {
+        let response = self.get(&device_list_uri(master)).await?;
         trace!("获取到设备列表: {}", response.data);
 
         Ok(response)
@@ -69,21 +403,28 @@ impl Xiaoai {
     /// 小爱服务的通用 GET 请求。
     ///
     /// API 服务器会和 `uri` 做 [`Url::join`]。
+    #[instrument(level = "debug", skip(self), fields(request_id = Empty, code = Empty, elapsed_ms = Empty))]
     pub async fn get(&self, uri: &str) -> crate::Result<XiaoaiResponse> {
         let request_id = random_request_id();
+        Span::current().record("request_id", &request_id);
         let url =
-            Url::parse_with_params(self.server.join(uri)?.as_str(), [("requestId", request_id)])?;
-        let response = self
-            .client
-            .get(url)
-            .send()
-            .await?
-            .error_for_status()?
-            .json::<XiaoaiResponse>()
-            .await?
-            .error_for_code()?;
-
-        Ok(response)
+            Url::parse_with_params(self.config.api_server.join(uri)?.as_str(), [("requestId", request_id)])?;
+
+        let mut retries = 0;
+        let mut backoff = RETRY_BACKOFF;
+        loop {
+            match self.send(self.client.get(url.clone())).await {
+                Err(crate::Error::Reqwest(e))
+                    if retries < self.config.max_retries && is_transient(&e) =>
+                {
+                    retries += 1;
+                    warn!("GET {} 失败，{:?} 后第 {} 次重试：{}", uri, backoff, retries, e);
+                    sleep(backoff).await;
+                    backoff *= 2;
+                }
+                result => return result,
+            }
+        }
     }
 
     /// 小爱服务的通用 POST 请求。
@@ -95,20 +436,191 @@ impl Xiaoai {
         mut form: HashMap<&str, &str>,
     ) -> crate::Result<XiaoaiResponse> {
         let request_id = random_request_id();
+        // form 借用了 request_id，这里手动创建 span 而不用 #[instrument]
+        let span = debug_span!(
+            "post",
+            uri,
+            request_id,
+            device_id = form.get("deviceId"),
+            code = Empty,
+            elapsed_ms = Empty,
+        );
         form.insert("requestId", &request_id);
-        let url = self.server.join(uri)?;
-        let response = self
-            .client
-            .post(url)
-            .form(&form)
-            .send()
-            .await?
+        let url = self.config.api_server.join(uri)?;
+        self.send(self.client.post(url).form(&form))
+            .instrument(span)
+            .await
+    }
+
+    /// 发送请求并校验响应，识别登录状态失效和被限流的情况。
+    ///
+    /// 耗时和响应中的 `code` 会记录到 [`Xiaoai::get`]、[`Xiaoai::post`] 的 span 上。
+    async fn send(&self, request: RequestBuilder) -> crate::Result<XiaoaiResponse> {
+        let started = Instant::now();
+        let replay = self
+            .config
+            .retry_on_rate_limit
+            .then(|| request.try_clone())
+            .flatten();
+        let mut result = self.send_once(request).await;
+        let wait = match &result {
+            Err(crate::Error::RateLimited { retry_after }) => {
+                Some(retry_after.unwrap_or(RATE_LIMIT_BACKOFF))
+            }
+            _ => None,
+        };
+        if let (Some(wait), Some(replay)) = (wait, replay) {
+            if wait <= MAX_RATE_LIMIT_WAIT {
+                warn!("请求被限流，{:?} 后重试一次", wait);
+                sleep(wait).await;
+                result = self.send_once(replay).await;
+            }
+        }
+        let span = Span::current();
+        span.record("elapsed_ms", started.elapsed().as_millis() as u64);
+        match &result {
+            Ok(response) => {
+                span.record("code", response.code);
+            }
+            Err(crate::Error::Api(response)) => {
+                span.record("code", response.code);
+            }
+            Err(_) => {}
+        }
+        debug!("请求结束");
+
+        result
+    }
+
+    /// 发送一次请求，开启了自动重新登录时经过 [`Xiaoai::send_with_relogin`]。
+    async fn send_once(&self, request: RequestBuilder) -> crate::Result<XiaoaiResponse> {
+        match &self.relogin {
+            Some(relogin) => self.send_with_relogin(relogin, request).await,
+            None => self.send_inner(request).await,
+        }
+    }
+
+    /// 同 [`Xiaoai::send_inner`]，但登录状态失效时重新登录一次并重放请求。
+    async fn send_with_relogin(
+        &self,
+        relogin: &Relogin,
+        request: RequestBuilder,
+    ) -> crate::Result<XiaoaiResponse> {
+        let generation = relogin.generation.load(Ordering::Acquire);
+        let Some(replay) = request.try_clone() else {
+            return self.send_inner(request).await;
+        };
+
+        match self.send_inner(request).await {
+            Err(crate::Error::SessionExpired) => {
+                self.relogin(relogin, generation).await?;
+                self.send_inner(replay).await
+            }
+            result => result,
+        }
+    }
+
+    /// 重新登录，并把新的 Cookies 换入当前的登录状态。
+    ///
+    /// `generation` 为请求发出时的登录次数，等锁期间已经有其他请求重新登录时直接返回。
+    async fn relogin(&self, relogin: &Relogin, generation: u64) -> crate::Result<()> {
+        let mut last_failure = relogin.last_failure.lock().await;
+        if relogin.generation.load(Ordering::Acquire) != generation {
+            return Ok(());
+        }
+        if last_failure.is_some_and(|at| at.elapsed() < RELOGIN_COOLDOWN) {
+            return Err(crate::Error::SessionExpired);
+        }
+
+        warn!("登录状态已失效，正在重新登录");
+        let result = async {
+            let login = Login::new(relogin.username.as_str(), &relogin.password)?
+                .with_config(self.config.clone())?;
+            complete_login(&login).await?;
+            Ok(login.into_cookie_store())
+        }
+        .await;
+        let cookie_store: Arc<CookieStoreMutex> = match result {
+            Ok(cookie_store) => cookie_store,
+            Err(e) => {
+                warn!("重新登录失败: {}", e);
+                *last_failure = Some(Instant::now());
+                return Err(e);
+            }
+        };
+
+        let cookies = cookie_store.lock().unwrap().clone();
+        *self.cookie_store.lock().unwrap() = cookies;
+        *last_failure = None;
+        relogin.generation.fetch_add(1, Ordering::Release);
+        debug!("重新登录成功");
+        if let Some(OnRelogin(callback)) = &self.on_relogin {
+            callback(self);
+        }
+
+        Ok(())
+    }
+
+    async fn send_inner(&self, request: RequestBuilder) -> crate::Result<XiaoaiResponse> {
+        let response = request.send().await?;
+        // 登录状态失效时，服务端返回 401，或者把请求重定向到登录页
+        let redirected_to_login = response
+            .url()
+            .host_str()
+            .is_some_and(|host| host.starts_with("account."));
+        if response.status() == StatusCode::UNAUTHORIZED || redirected_to_login {
+            return Err(crate::Error::SessionExpired);
+        }
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Err(crate::Error::RateLimited {
+                retry_after: retry_after(&response),
+            });
+        }
+
+        let response = response
             .error_for_status()?
             .json::<XiaoaiResponse>()
-            .await?
-            .error_for_code()?;
+            .await?;
+        if response.code == 401 {
+            return Err(crate::Error::SessionExpired);
+        }
+        if response.code == RATE_LIMITED_CODE {
+            return Err(crate::Error::RateLimited { retry_after: None });
+        }
 
-        Ok(response)
+        response.error_for_code()
+    }
+
+    /// 登录状态中 `serviceToken`、`userId` 最早的过期时间。
+    ///
+    /// 只读取本地保存的 cookie，不发送请求。这些 cookie 都是会话 cookie（没有过期时间）或不存在时返回 `None`。
+    /// 服务端也可能提前让登录状态失效，过期时间只能作为安排重新登录的参考。
+    pub fn token_expiry(&self) -> Option<OffsetDateTime> {
+        let cookie_store = self.cookie_store.lock().unwrap();
+        cookie_store
+            .matches(&self.config.api_server)
+            .into_iter()
+            .filter(|cookie| matches!(cookie.name(), "serviceToken" | "userId"))
+            .filter_map(|cookie| match &cookie.expires {
+                CookieExpiration::AtUtc(time) => Some(*time),
+                CookieExpiration::SessionEnd => None,
+            })
+            .min()
+    }
+
+    /// 检查登录状态是否仍然有效。
+    ///
+    /// 会发送一次获取设备列表的请求。
+    ///
+    /// # Errors
+    ///
+    /// 登录状态失效时返回 `Ok(false)`，网络错误等其他情况才会返回错误。
+    pub async fn is_logged_in(&self) -> crate::Result<bool> {
+        match self.raw_device_info().await {
+            Ok(_) => Ok(true),
+            Err(crate::Error::SessionExpired) => Ok(false),
+            Err(e) => Err(e),
+        }
     }
 
     /// 保存登录状态到 `writer`。
@@ -116,32 +628,194 @@ impl Xiaoai {
     /// 状态被保存为明文的 json，请注意安全性。参见
     /// [`cookie_store::serde::json::save_incl_expired_and_nonpersistent`]。
     ///
+    /// # Errors
+    ///
+    /// 写入失败时，将返回 [`Error::CookieStore`][crate::Error::CookieStore]。
+    ///
     /// # Panics
     ///
     /// 当内部发生锁中毒时会 panic。
-    pub fn save<W: Write>(&self, writer: &mut W) -> cookie_store::Result<()> {
+    pub fn save<W: Write>(&self, writer: &mut W) -> crate::Result<()> {
         save_incl_expired_and_nonpersistent(&self.cookie_store.lock().unwrap(), writer)
+            .map_err(crate::Error::CookieStore)
+    }
+
+    /// 同 [`Xiaoai::save`]，但只保存未过期且持久化的 Cookies，文件会更精简。
+    ///
+    /// 注意 `serviceToken` 等关键 Cookie 可能被服务端标记为非持久化（会话 Cookie），
+    /// 此时用这种方式保存的登录状态在加载后无法使用，这也是 [`Xiaoai::save`]
+    /// 默认保存全部 Cookies 的原因。参见 [`cookie_store::serde::json::save`]。
+    ///
+    /// # Errors
+    ///
+    /// 写入失败时，将返回 [`Error::CookieStore`][crate::Error::CookieStore]。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    pub fn save_persistent_only<W: Write>(&self, writer: &mut W) -> crate::Result<()> {
+        save(&self.cookie_store.lock().unwrap(), writer).map_err(crate::Error::CookieStore)
     }
 
     /// 从 `reader` 加载登录状态。
     ///
     /// **不会**验证登录状态的有效性，如果在请求时出错，请尝试重新
     /// [`login`][Xiaoai::login]。另请参见 [`cookie_store::serde::json::load_all`]。
-    pub fn load<R: BufRead>(reader: R) -> cookie_store::Result<Self> {
-        let cookie_store = Arc::new(CookieStoreMutex::new(load_all(reader)?));
-        let client = Client::builder()
-            .user_agent(API_UA)
-            .cookie_provider(Arc::clone(&cookie_store))
-            .build()?;
+    ///
+    /// # Errors
+    ///
+    /// - 读取或解析失败时，将返回 [`Error::CookieStore`][crate::Error::CookieStore]。
+    /// - 登录状态是用 [`save_encrypted`][Xiaoai::save_encrypted] 保存的，将返回
+    ///   [`Error::Encrypted`][crate::Error::Encrypted]，请改用 [`load_encrypted`][Xiaoai::load_encrypted]。
+    pub fn load<R: BufRead>(mut reader: R) -> crate::Result<Self> {
+        let buf = reader
+            .fill_buf()
+            .map_err(|e| crate::Error::CookieStore(Box::new(e)))?;
+        if crate::crypto::is_encrypted(buf) {
+            return Err(crate::Error::Encrypted);
+        }
 
-        Ok(Self {
-            client,
-            cookie_store,
-            server: Url::parse(API_SERVER)?,
+        let cookie_store = load_all(reader).map_err(crate::Error::CookieStore)?;
+        let cookie_store = Arc::new(CookieStoreMutex::new(cookie_store));
+
+        Self::from_cookie_store(cookie_store, ClientConfig::default())
+    }
+
+    /// 同 [`Xiaoai::save`]，但使用 `passphrase` 加密后再写入。
+    ///
+    /// 使用 PBKDF2 由口令派生密钥，再以 AES-256-GCM 加密，文件以固定的 magic header 开头，
+    /// 便于 [`load_encrypted`][Xiaoai::load_encrypted] 识别。
+    ///
+    /// # Errors
+    ///
+    /// 加密或写入失败时，将返回 [`Error::Crypto`][crate::Error::Crypto] 或
+    /// [`Error::CookieStore`][crate::Error::CookieStore]。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    pub fn save_encrypted<W: Write>(&self, writer: &mut W, passphrase: &str) -> crate::Result<()> {
+        let mut plain = Vec::new();
+        self.save(&mut plain)?;
+        let encrypted = crate::crypto::encrypt(&plain, passphrase)?;
+
+        writer
+            .write_all(&encrypted)
+            .map_err(|e| crate::Error::CookieStore(Box::new(e)))
+    }
+
+    /// 加载 [`save_encrypted`][Xiaoai::save_encrypted] 保存的登录状态。
+    ///
+    /// 会自动识别明文和密文，明文的登录状态会忽略 `passphrase` 直接加载。
+    ///
+    /// # Errors
+    ///
+    /// 口令错误或文件损坏时，将返回 [`Error::Crypto`][crate::Error::Crypto]，其他同 [`Xiaoai::load`]。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::Xiaoai;
+    /// let xiaoai = Xiaoai::from_json_str("[]").unwrap();
+    /// let mut encrypted = Vec::new();
+    /// xiaoai.save_encrypted(&mut encrypted, "口令").unwrap();
+    ///
+    /// assert!(Xiaoai::load(encrypted.as_slice()).is_err());
+    /// assert!(Xiaoai::load_encrypted(encrypted.as_slice(), "错误的口令").is_err());
+    /// assert!(Xiaoai::load_encrypted(encrypted.as_slice(), "口令").is_ok());
+    /// ```
+    pub fn load_encrypted<R: BufRead>(mut reader: R, passphrase: &str) -> crate::Result<Self> {
+        let mut data = Vec::new();
+        reader
+            .read_to_end(&mut data)
+            .map_err(|e| crate::Error::CookieStore(Box::new(e)))?;
+        if !crate::crypto::is_encrypted(&data) {
+            return Self::load(data.as_slice());
+        }
+
+        Self::load(crate::crypto::decrypt(&data, passphrase)?.as_slice())
+    }
+
+    /// 同 [`Xiaoai::save`]，但直接返回 json 字符串。
+    ///
+    /// 便于通过环境变量、其他 API 等传递登录状态，同样请注意安全性。
+    ///
+    /// # Errors
+    ///
+    /// 序列化失败时，将返回 [`Error::CookieStore`][crate::Error::CookieStore]。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use miai::Xiaoai;
+    /// let xiaoai = Xiaoai::from_json_str("[]").unwrap();
+    /// let json = xiaoai.to_json_string().unwrap();
+    /// assert!(Xiaoai::from_json_str(&json).is_ok());
+    /// ```
+    pub fn to_json_string(&self) -> crate::Result<String> {
+        let mut buf = Vec::new();
+        self.save(&mut buf)?;
+
+        String::from_utf8(buf).map_err(|e| crate::Error::CookieStore(Box::new(e)))
+    }
+
+    /// 同 [`Xiaoai::load`]，但从 json 字符串加载登录状态。
+    ///
+    /// # Errors
+    ///
+    /// 解析失败时，将返回 [`Error::CookieStore`][crate::Error::CookieStore]。
+    pub fn from_json_str(json: &str) -> crate::Result<Self> {
+        Self::load(json.as_bytes())
+    }
+
+    /// 当前登录账号的用户 ID，取自登录状态中的 `userId`。
+    ///
+    /// # Panics
+    ///
+    /// 当内部发生锁中毒时会 panic。
+    pub fn user_id(&self) -> Option<String> {
+        self.cookie_value("userId")
+    }
+
+    /// 获取当前登录账号的信息。
+    ///
+    /// 用户 ID 取自登录状态，同时会请求一次设备列表，请求成功也说明登录状态仍然有效。
+    /// 小爱服务没有提供查询昵称的接口，需要区分账号时可以参考账号下的设备。
+    ///
+    /// # Errors
+    ///
+    /// 登录状态中没有 `userId` 时，将返回 [`Error::MissingCookie`][crate::Error::MissingCookie]。
+    pub async fn account_info(&self) -> crate::Result<AccountInfo> {
+        let user_id = self
+            .user_id()
+            .ok_or(crate::Error::MissingCookie("userId"))?;
+        let devices = self.device_info().await?;
+
+        Ok(AccountInfo {
+            user_id,
+            devices,
+            token_expiry: self.token_expiry(),
         })
     }
 
+    /// 读取会发往 API 服务器的 cookie 的值。
+    fn cookie_value(&self, name: &str) -> Option<String> {
+        let cookie_store = self.cookie_store.lock().unwrap();
+        cookie_store
+            .matches(&self.config.api_server)
+            .into_iter()
+            .find(|cookie| cookie.name() == name)
+            .map(|cookie| cookie.value().to_string())
+    }
+
     /// 向小爱设备发送 OpenWrt UBUS 调用请求。
+    ///
+    /// `message` 为 JSON 字符串，可以用 [`UbusMessage`] 构造。
+    #[instrument(level = "debug", skip(self, message))]
     pub async fn ubus_call(
         &self,
         device_id: &str,
@@ -160,106 +834,422 @@ impl Xiaoai {
     }
 
     /// 请求小爱设备播报文本。
+    ///
+    /// 文本经过 JSON 转义后原样发送，换行、emoji、引号等字符都会完整送达设备，
+    /// 但设备如何朗读这些字符取决于固件。
     pub async fn tts(&self, device_id: &str, text: &str) -> crate::Result<XiaoaiResponse> {
-        let message = json!({"text": text}).to_string();
+        self.tts_with(device_id, text, &TtsOptions::default()).await
+    }
 
-        self.ubus_call(device_id, "mibrain", "text_to_speech", &message)
+    /// 同 [`Xiaoai::tts`]，但可以设置语速和音色。
+    ///
+    /// 设置的参数会一并发送给设备，但不同机型、固件的支持程度不同，不支持时会被忽略，只能尽力而为。
+    pub async fn tts_with(
+        &self,
+        device_id: &str,
+        text: &str,
+        options: &TtsOptions,
+    ) -> crate::Result<XiaoaiResponse> {
+        let mut message = UbusMessage::new().text(text);
+        if let Some(speed) = options.speed {
+            message = message.field("speed", speed);
+        }
+        if let Some(speaker) = &options.speaker {
+            message = message.field("speaker", speaker.as_str());
+        }
+
+        self.ubus_call(device_id, "mibrain", "text_to_speech", &message.to_json_string())
             .await
     }
 
+    /// 请求小爱设备播报 SSML。
+    ///
+    /// 小爱并不直接支持 SSML，此方法会先用 [`ssml::to_text`][crate::ssml::to_text]
+    /// 把支持的子集（停顿、强调等）转换为纯文本，再调用 [`Xiaoai::tts`]。
+    pub async fn tts_ssml(&self, device_id: &str, ssml: &str) -> crate::Result<XiaoaiResponse> {
+        self.tts(device_id, &crate::ssml::to_text(ssml)).await
+    }
+
     /// 请求小爱播放 `url`。
+    ///
+    /// `type` 字段使用 [`DEFAULT_PLAY_URL_TYPE`][crate::model::DEFAULT_PLAY_URL_TYPE]，
+    /// 需要按机型选择播放方式时使用 [`Xiaoai::play_url_for`]。
     pub async fn play_url(&self, device_id: &str, url: &str) -> crate::Result<XiaoaiResponse> {
+        self.play_url_with_type(device_id, url, DEFAULT_PLAY_URL_TYPE)
+            .await
+    }
+
+    /// 同 [`Xiaoai::play_url`]，但指定 `type` 字段。
+    pub async fn play_url_with_type(
+        &self,
+        device_id: &str,
+        url: &str,
+        play_type: u32,
+    ) -> crate::Result<XiaoaiResponse> {
+        // type 字段不仅能控制亮灯行为，还能控制暂停行为？
+        // 比如在机型 L16A 上，设为 3 才能有完整的播放、暂停控制，但无法停止
+        // 设为 0、1 可以播放、停止，但暂停后就无法恢复，设为 2 则无法暂停
+        // 貌似每个机型都不太一样，参考 https://github.com/yihong0618/MiService/issues/30
+        let message = UbusMessage::media_player()
+            .url(url)
+            .kind(play_type)
+            .to_json_string();
+
+        self.ubus_call(device_id, "mediaplayer", "player_play_url", &message)
+            .await
+    }
+
+    /// 按机型选择合适的方式播放 `url`。
+    ///
+    /// 根据 [`model`][crate::model] 中的机型信息，选择 [`Xiaoai::play_url_with_type`]
+    /// 或 [`Xiaoai::play_music`]，未收录的机型使用 [`Xiaoai::play_url`]。
+    pub async fn play_url_for(&self, device: &DeviceInfo, url: &str) -> crate::Result<XiaoaiResponse> {
+        let model = device_model(device);
+        trace!("机型 {} 使用 {:?} 播放", device.hardware, model.play_method);
+        match model.play_method {
+            PlayMethod::Url => self.play_url(&device.device_id, url).await,
+            PlayMethod::Music => self.play_music(&device.device_id, url).await,
+        }
+    }
+
+    /// 请求小爱播放内置的网络电台。
+    ///
+    /// `name` 可以是电台的标识或名称，参见 [`radio::STATIONS`][crate::radio::STATIONS]。
+    ///
+    /// # Errors
+    ///
+    /// 找不到对应的电台时，将返回 [`Error::UnknownStation`][crate::Error::UnknownStation]。
+    pub async fn play_station(&self, device_id: &str, name: &str) -> crate::Result<XiaoaiResponse> {
+        let station = find_station(name)
+            .ok_or_else(|| crate::Error::UnknownStation(name.to_string()))?;
+        trace!("播放电台 {}: {}", station.name, station.url);
+
+        self.play_url(device_id, station.url).await
+    }
+
+    /// 请求小爱播放音乐。
+    ///
+    /// 和 [`Xiaoai::play_url`] 相比，此方法针对音频特化，能支持更多参数，但并非所有机型都支持。
+    /// 目前尚不支持配置这些参数，仅用作播放音乐的另一种方案。
+    pub async fn play_music(&self, device_id: &str, url: &str) -> crate::Result<XiaoaiResponse> {
+        const AUDIO_ID: &str = "1582971365183456177";
+        const ID: &str = "355454500";
         let message = json!({
-            "url": url,
-            // type 字段不仅能控制亮灯行为，还能控制暂停行为？
-            // 比如在机型 L16A 上，设为 3 才能有完整的播放、暂停控制，但无法停止
-            // 设为 0、1 可以播放、停止，但暂停后就无法恢复，设为 2 则无法暂停
-            // 貌似每个机型都不太一样，参考 https://github.com/yihong0618/MiService/issues/30
-            "type": 3,
-            "media": "app_ios"
+            "startaudioid": AUDIO_ID,
+            "music": {
+                "payload": {
+                    // 来自 miservice:
+                    // If set to "MUSIC", the light will be on
+                    // "audio_type": "MUSIC",
+                    "audio_items": [
+                        {
+                            "item_id": {
+                                "audio_id": AUDIO_ID,
+                                "cp": {
+                                    "album_id": "-1",
+                                    "episode_index": 0,
+                                    "id": ID,
+                                    "name": "xiaowei",
+                                },
+                            },
+                            "stream": {"url": url},
+                        }
+                    ],
+                    "list_params": {
+                        "listId": "-1",
+                        "loadmore_offset": 0,
+                        "origin": "xiaowei",
+                        "type": "MUSIC",
+                    },
+                },
+                "play_behavior": "REPLACE_ALL",
+            }
         })
         .to_string();
 
-        self.ubus_call(device_id, "mediaplayer", "player_play_url", &message)
-            .await
+        self.ubus_call(device_id, "mediaplayer", "player_play_music", &message)
+            .await
+    }
+
+    /// 请求小爱调整音量。
+    ///
+    /// # Errors
+    ///
+    /// 音量超出 [`VOLUME_RANGE`][crate::model::VOLUME_RANGE] 时，将返回
+    /// [`Error::InvalidVolume`][crate::Error::InvalidVolume]，不会发送请求。
+    pub async fn set_volume(&self, device_id: &str, volume: u32) -> crate::Result<XiaoaiResponse> {
+        if !VOLUME_RANGE.contains(&volume) {
+            return Err(crate::Error::InvalidVolume(volume));
+        }
+        let message = UbusMessage::media_player().volume(volume).to_json_string();
+
+        self.ubus_call(device_id, "mediaplayer", "player_set_volume", &message)
+            .await
+    }
+
+    /// 获取当前音量。
+    ///
+    /// 结果取自 [`PlayerStatus::volume`]。
+    ///
+    /// # Errors
+    ///
+    /// 无法从状态中解析出音量时，将返回 [`Error::StatusUnavailable`][crate::Error::StatusUnavailable]。
+    pub async fn get_volume(&self, device_id: &str) -> crate::Result<u32> {
+        let status = self.player_status_parsed(device_id).await?;
+        match status.volume() {
+            Some(volume) => Ok(volume),
+            None => Err(crate::Error::StatusUnavailable(status.raw)),
+        }
+    }
+
+    /// 请求小爱静音，返回静音前的音量。
+    ///
+    /// [`Xiaoai`] 不保存状态，需要由调用方保存返回的音量，之后传给 [`Xiaoai::unmute`] 恢复。
+    /// 已经静音时不会再设置音量，返回 `0`。
+    ///
+    /// ```no_run
+    /// # async fn example(xiaoai: miai::Xiaoai) -> miai::Result<()> {
+    /// let volume = xiaoai.mute("device_id").await?;
+    /// // ...
+    /// xiaoai.unmute("device_id", volume).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// 无法读取当前音量时，将返回 [`Error::StatusUnavailable`][crate::Error::StatusUnavailable]。
+    pub async fn mute(&self, device_id: &str) -> crate::Result<u32> {
+        let volume = self.get_volume(device_id).await?;
+        if volume > 0 {
+            self.set_volume(device_id, 0).await?;
+        }
+
+        Ok(volume)
+    }
+
+    /// 请求小爱取消静音，恢复为 [`Xiaoai::mute`] 返回的音量。
+    pub async fn unmute(&self, device_id: &str, volume: u32) -> crate::Result<XiaoaiResponse> {
+        self.set_volume(device_id, volume).await
+    }
+
+    /// 在 `duration` 内分 `steps` 步把音量从当前值平滑调整到 `target`，返回最后一次设置的响应。
+    ///
+    /// `target` 会被限制在 [`VOLUME_RANGE`][crate::model::VOLUME_RANGE] 之内，`steps` 为 `0` 时立即设置为目标音量。
+    /// 每步之间等待 `duration / steps`，舍入后与上一步相同的音量会被跳过。
+    /// 丢弃返回的 future（如放进 `tokio::select!` 或 abort 所在任务）即可取消，音量停留在当前这一步。
+    ///
+    /// ```no_run
+    /// # async fn example(xiaoai: miai::Xiaoai) -> miai::Result<()> {
+    /// use std::time::Duration;
+    ///
+    /// // 30 秒内渐弱到静音
+    /// xiaoai.fade_volume("device_id", 0, Duration::from_secs(30), 30).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// 无法读取当前音量时，将返回 [`Error::StatusUnavailable`][crate::Error::StatusUnavailable]。
+    pub async fn fade_volume(
+        &self,
+        device_id: &str,
+        target: u32,
+        duration: Duration,
+        steps: u32,
+    ) -> crate::Result<XiaoaiResponse> {
+        let target = target.clamp(*VOLUME_RANGE.start(), *VOLUME_RANGE.end());
+        if steps == 0 {
+            return self.set_volume(device_id, target).await;
+        }
+
+        let start = i64::from(self.get_volume(device_id).await?);
+        let distance = i64::from(target) - start;
+        let interval = duration / steps;
+        let mut last = start;
+        for step in 1..steps {
+            sleep(interval).await;
+            let volume = start + distance * i64::from(step) / i64::from(steps);
+            if volume != last {
+                self.set_volume(device_id, volume as u32).await?;
+                last = volume;
+            }
+        }
+        sleep(interval).await;
+
+        self.set_volume(device_id, target).await
+    }
+
+    /// 请求小爱相对调整音量。
+    ///
+    /// 先读取当前音量，加上 `delta` 后限制在 [`VOLUME_RANGE`][crate::model::VOLUME_RANGE]
+    /// 之内再设置。
+    ///
+    /// # Errors
+    ///
+    /// 无法读取当前音量时，将返回 [`Error::StatusUnavailable`][crate::Error::StatusUnavailable]。
+    pub async fn adjust_volume(&self, device_id: &str, delta: i32) -> crate::Result<XiaoaiResponse> {
+        let current = self.get_volume(device_id).await?;
+        let volume = (i64::from(current) + i64::from(delta))
+            .clamp(i64::from(*VOLUME_RANGE.start()), i64::from(*VOLUME_RANGE.end()))
+            as u32;
+
+        self.set_volume(device_id, volume).await
+    }
+
+    /// 请求小爱执行文本。
+    ///
+    /// 效果和口头询问一样。
+    pub async fn nlp(&self, device_id: &str, text: &str) -> crate::Result<XiaoaiResponse> {
+        self.nlp_with(device_id, text, true).await
+    }
+
+    /// 同 [`Xiaoai::nlp`]，但可以选择小爱是否出声回应。
+    ///
+    /// `speak` 为 `false` 时只执行指令（如触发场景），小爱不会播报回复。
+    pub async fn nlp_with(
+        &self,
+        device_id: &str,
+        text: &str,
+        speak: bool,
+    ) -> crate::Result<XiaoaiResponse> {
+        let message = UbusMessage::new()
+            .field("tts", u8::from(speak))
+            .field("nlp", 1)
+            .field("nlp_text", text)
+            .to_json_string();
+
+        self.ubus_call(device_id, "mibrain", "ai_service", &message)
+            .await
+    }
+
+    /// 请求小爱设置闹钟。
+    ///
+    /// 闹钟通过语音指令设置，小爱会播报设置的结果，参见 [`alarm`][crate::alarm]。
+    pub async fn create_alarm(
+        &self,
+        device_id: &str,
+        spec: &AlarmSpec,
+    ) -> crate::Result<XiaoaiResponse> {
+        let command = spec.command();
+        trace!("设置闹钟: {}", command);
+
+        self.nlp(device_id, &command).await
+    }
+
+    /// 询问小爱设置了哪些闹钟。
+    ///
+    /// 没有可以查询的接口，这里通过 [`Xiaoai::ask`] 询问，闹钟列表以小爱的回答给出，
+    /// 见 [`NlpResult::answer`]。
+    pub async fn list_alarms(&self, device_id: &str) -> crate::Result<NlpResult> {
+        self.ask(device_id, alarm::LIST_COMMAND).await
+    }
+
+    /// 请求小爱删除和 `spec` 的时间、重复规则相同的闹钟。
+    ///
+    /// 闹钟没有 ID，只能通过语音指令按时间删除，参见 [`alarm`][crate::alarm]。
+    pub async fn delete_alarm(
+        &self,
+        device_id: &str,
+        spec: &AlarmSpec,
+    ) -> crate::Result<XiaoaiResponse> {
+        let command = spec.delete_command();
+        trace!("删除闹钟: {}", command);
+
+        self.nlp(device_id, &command).await
+    }
+
+    /// 请求小爱删除所有闹钟。
+    pub async fn delete_all_alarms(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+        self.nlp(device_id, alarm::DELETE_ALL_COMMAND).await
+    }
+
+    /// 请求小爱开启或关闭勿扰模式。
+    ///
+    /// 开启且同时给出 `start` 和 `end` 时设置勿扰时间段。设置通过语音指令完成，
+    /// 小爱会播报设置的结果，参见 [`dnd`][crate::dnd]。
+    pub async fn set_do_not_disturb(
+        &self,
+        device_id: &str,
+        enabled: bool,
+        start: Option<Time>,
+        end: Option<Time>,
+    ) -> crate::Result<XiaoaiResponse> {
+        let command = dnd::command(enabled, start, end);
+        trace!("设置勿扰模式: {}", command);
+
+        self.nlp(device_id, &command).await
+    }
+
+    /// 询问小爱勿扰模式的状态。
+    ///
+    /// 没有可以查询的接口，这里通过 [`Xiaoai::ask`] 询问，状态以小爱的回答给出，
+    /// 见 [`NlpResult::answer`]。
+    pub async fn get_do_not_disturb(&self, device_id: &str) -> crate::Result<NlpResult> {
+        self.ask(device_id, dnd::QUERY_COMMAND).await
+    }
+
+    /// 请求小爱切换输入源。
+    ///
+    /// 通过语音指令切换，小爱会播报切换的结果，参见 [`source`][crate::source]。
+    pub async fn set_source(&self, device_id: &str, source: Source) -> crate::Result<XiaoaiResponse> {
+        let command = source::command(source);
+        trace!("切换输入源: {}", command);
+
+        self.nlp(device_id, command).await
     }
 
-    /// 请求小爱播放音乐。
+    /// 询问小爱当前的输入源。
     ///
-    /// 和 [`Xiaoai::play_url`] 相比，此方法针对音频特化，能支持更多参数，但并非所有机型都支持。
-    /// 目前尚不支持配置这些参数，仅用作播放音乐的另一种方案。
-    pub async fn play_music(&self, device_id: &str, url: &str) -> crate::Result<XiaoaiResponse> {
-        const AUDIO_ID: &str = "1582971365183456177";
-        const ID: &str = "355454500";
-        let message = json!({
-            "startaudioid": AUDIO_ID,
-            "music": {
-                "payload": {
-                    // 来自 miservice:
-                    // If set to "MUSIC", the light will be on
-                    // "audio_type": "MUSIC",
-                    "audio_items": [
-                        {
-                            "item_id": {
-                                "audio_id": AUDIO_ID,
-                                "cp": {
-                                    "album_id": "-1",
-                                    "episode_index": 0,
-                                    "id": ID,
-                                    "name": "xiaowei",
-                                },
-                            },
-                            "stream": {"url": url},
-                        }
-                    ],
-                    "list_params": {
-                        "listId": "-1",
-                        "loadmore_offset": 0,
-                        "origin": "xiaowei",
-                        "type": "MUSIC",
-                    },
-                },
-                "play_behavior": "REPLACE_ALL",
-            }
-        })
-        .to_string();
+    /// 没有可以查询的接口，这里通过 [`Xiaoai::ask`] 询问，结果以小爱的回答给出，
+    /// 见 [`NlpResult::answer`]。
+    pub async fn get_source(&self, device_id: &str) -> crate::Result<NlpResult> {
+        self.ask(device_id, source::QUERY_COMMAND).await
+    }
 
-        self.ubus_call(device_id, "mediaplayer", "player_play_music", &message)
-            .await
+    /// 请求小爱在 `after` 后停止播放，即定时关闭。
+    ///
+    /// 通过语音指令设置，由设备计时，小爱会播报设置的结果，参见 [`sleep_timer`][crate::sleep_timer]。
+    /// 设备不支持时可以改用 [`Xiaoai::stop_after`]。
+    pub async fn set_sleep_timer(
+        &self,
+        device_id: &str,
+        after: Duration,
+    ) -> crate::Result<XiaoaiResponse> {
+        let command = sleep_timer::command(after);
+        trace!("设置定时关闭: {}", command);
+
+        self.nlp(device_id, &command).await
     }
 
-    /// 请求小爱调整音量。
-    pub async fn set_volume(&self, device_id: &str, volume: u32) -> crate::Result<XiaoaiResponse> {
-        let message = json!({
-            "volume": volume,
-            "media": "app_ios"
-        })
-        .to_string();
+    /// 请求小爱取消 [`Xiaoai::set_sleep_timer`] 设置的定时关闭。
+    pub async fn cancel_sleep_timer(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+        self.nlp(device_id, sleep_timer::CANCEL_COMMAND).await
+    }
 
-        self.ubus_call(device_id, "mediaplayer", "player_set_volume", &message)
-            .await
+    /// 询问小爱定时关闭的剩余时间。
+    ///
+    /// 没有可以查询的接口，这里通过 [`Xiaoai::ask`] 询问，剩余时间以小爱的回答给出，
+    /// 见 [`NlpResult::answer`]。
+    pub async fn get_sleep_timer(&self, device_id: &str) -> crate::Result<NlpResult> {
+        self.ask(device_id, sleep_timer::QUERY_COMMAND).await
     }
 
-    /// 请求小爱执行文本。
+    /// 在本地等待 `after` 后停止播放，用于不支持 [`Xiaoai::set_sleep_timer`] 的设备。
     ///
-    /// 效果和口头询问一样。
-    pub async fn nlp(&self, device_id: &str, text: &str) -> crate::Result<XiaoaiResponse> {
-        let message = json!({
-            "tts": 1,
-            "nlp": 1,
-            "nlp_text": text
-        })
-        .to_string();
+    /// 计时在调用方进行，调用方需要一直运行到计时结束；丢弃返回的 future 即可取消。
+    pub async fn stop_after(&self, device_id: &str, after: Duration) -> crate::Result<XiaoaiResponse> {
+        sleep(after).await;
 
-        self.ubus_call(device_id, "mibrain", "ai_service", &message)
-            .await
+        self.set_play_state(device_id, PlayState::Stop).await
     }
 
     /// 获取播放器的状态信息。
     ///
     /// 可能包含播放状态，音量和循环播放设置。
     pub async fn player_status(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
-        let message = json!({"media": "app_ios"}).to_string();
+        let message = UbusMessage::media_player().to_json_string();
 
         self.ubus_call(device_id, "mediaplayer", "player_get_play_status", &message)
             .await
@@ -284,7 +1274,18 @@ impl Xiaoai {
             }
         }
         
-        Ok(PlayerStatus { raw: data })
+        Ok(PlayerStatus::from_raw(data))
+    }
+
+    /// 播放器当前是否正在播放。
+    ///
+    /// 结果取自 [`PlayerStatus::play_state`]，即播放器状态中的 `status` 码。
+    ///
+    /// # Errors
+    ///
+    /// 无法从状态中解析出播放状态时，将返回 [`Error::StatusUnavailable`][crate::Error::StatusUnavailable]。
+    pub async fn is_playing(&self, device_id: &str) -> crate::Result<bool> {
+        Ok(self.play_state(device_id).await? == PlayState::Play)
     }
 
     /// 设置播放器的播放状态。
@@ -299,12 +1300,106 @@ impl Xiaoai {
             PlayState::Stop => "stop",
             PlayState::Toggle => "toggle",
         };
-        let message = json!({"action": action, "media": "app_ios"}).to_string();
+
+        self.play_operation(device_id, action).await
+    }
+
+    /// 同 [`Xiaoai::set_play_state`]，但会在操作后读取播放器状态，返回实际的播放状态。
+    ///
+    /// 设备状态的更新有延迟，读到的状态和预期不一致时会稍等后再读一次，仍不一致时返回实际读到的状态。
+    /// 对于 [`PlayState::Toggle`]，会先读取操作前的状态以推断预期，返回值即切换后的状态。
+    ///
+    /// # Errors
+    ///
+    /// 无法从状态中解析出播放状态时，将返回 [`Error::StatusUnavailable`][crate::Error::StatusUnavailable]。
+    pub async fn set_play_state_confirmed(
+        &self,
+        device_id: &str,
+        state: PlayState,
+    ) -> crate::Result<PlayState> {
+        let expected = match &state {
+            PlayState::Toggle => match self.player_status_parsed(device_id).await?.status {
+                Some(PlayState::Play) => Some(PlayState::Pause),
+                Some(_) => Some(PlayState::Play),
+                None => None,
+            },
+            state => Some(state.clone()),
+        };
+        self.set_play_state(device_id, state).await?;
+
+        sleep(CONFIRM_DELAY).await;
+        let mut actual = self.play_state(device_id).await?;
+        if expected.as_ref().is_some_and(|expected| *expected != actual) {
+            sleep(CONFIRM_DELAY).await;
+            actual = self.play_state(device_id).await?;
+            trace!("再次读取播放状态: 预期 {:?}，实际 {:?}", expected, actual);
+        }
+
+        Ok(actual)
+    }
+
+    /// 读取当前的播放状态。
+    async fn play_state(&self, device_id: &str) -> crate::Result<PlayState> {
+        let status = self.player_status_parsed(device_id).await?;
+        status
+            .status
+            .ok_or(crate::Error::StatusUnavailable(status.raw))
+    }
+
+    /// 请求小爱播放下一首。
+    ///
+    /// # Errors
+    ///
+    /// 并非所有机型都支持切歌，设备拒绝时将返回
+    /// [`Error::OperationUnsupported`][crate::Error::OperationUnsupported]。
+    pub async fn next_track(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+        self.track_operation(device_id, "next", "切换到下一首").await
+    }
+
+    /// 请求小爱播放上一首。
+    ///
+    /// # Errors
+    ///
+    /// 同 [`Xiaoai::next_track`]。
+    pub async fn prev_track(&self, device_id: &str) -> crate::Result<XiaoaiResponse> {
+        self.track_operation(device_id, "prev", "切换到上一首").await
+    }
+
+    async fn track_operation(
+        &self,
+        device_id: &str,
+        action: &str,
+        operation: &'static str,
+    ) -> crate::Result<XiaoaiResponse> {
+        self.play_operation(device_id, action)
+            .await
+            .map_err(|e| match e {
+                crate::Error::Api(response) => {
+                    crate::Error::OperationUnsupported { operation, response }
+                }
+                e => e,
+            })
+    }
+
+    async fn play_operation(&self, device_id: &str, action: &str) -> crate::Result<XiaoaiResponse> {
+        let message = UbusMessage::media_player().action(action).to_json_string();
 
         self.ubus_call(device_id, "mediaplayer", "player_play_operation", &message)
             .await
     }
 
+    /// 设置播放器的循环模式。
+    pub async fn set_loop_mode(
+        &self,
+        device_id: &str,
+        mode: LoopMode,
+    ) -> crate::Result<XiaoaiResponse> {
+        let message = UbusMessage::media_player().kind(loop_code(mode)).to_json_string();
+
+        self.ubus_call(device_id, "mediaplayer", "player_set_loop", &message)
+            .await
+    }
+
     /// 获取小爱音箱最近收到的消息和对话记录（旧方法 - 使用 ubus API）。
     ///
     /// 该方法使用 ubus 调用获取 NLP 结果，但由于小米服务器的数据保留时间极短，
@@ -330,7 +1425,7 @@ impl Xiaoai {
 
         let mut messages = Vec::new();
         for item in result_array.unwrap() {
-            if !item.get("nlp").and_then(|v| v.as_str()).is_some() {
+            if item.get("nlp").and_then(|v| v.as_str()).is_none() {
                 trace!("跳过无效 item: {}", item);
                 continue;
             }
@@ -372,6 +1467,65 @@ impl Xiaoai {
         Ok(messages)
     }
 
+    /// 获取小爱音箱最近一次对话。
+    ///
+    /// 只请求一次 `nlp_result_get`，适合在脚本中单次查询。和 [`Xiaoai::get_messages`] 一样，
+    /// 服务端的数据保留时间很短，没有对话时返回 `Ok(None)`。
+    ///
+    /// # Errors
+    ///
+    /// 返回的结构无法解析时，将返回 [`Error::ConversationUnavailable`][crate::Error::ConversationUnavailable]，
+    /// 其中带有无法解析的原始片段。
+    pub async fn last_conversation(&self, device_id: &str) -> crate::Result<Option<Conversation>> {
+        let response = self
+            .ubus_call(device_id, "mibrain", "nlp_result_get", "{}")
+            .await?;
+        trace!("获取最近对话: {}", response.data);
+
+        parse_nlp_result(&response.data)
+    }
+
+    /// 询问小爱，并等待解析出结构化的回答。
+    ///
+    /// 发出请求后会轮询 `nlp_result_get`，直到出现比请求前更新的对话，最多等待约 5 秒。
+    /// 不同意图域返回的结构差别很大，无法识别的字段为 `None`，完整内容始终保留在
+    /// [`NlpResult::raw`] 中；等不到新的对话时，`raw` 为执行请求的原始返回。
+    pub async fn ask(&self, device_id: &str, text: &str) -> crate::Result<NlpResult> {
+        let latest_time = |data: &Value| {
+            nlp_records(data)
+                .ok()
+                .and_then(|records| records.iter().map(nlp_timestamp).max())
+        };
+        let before = self
+            .ubus_call(device_id, "mibrain", "nlp_result_get", "{}")
+            .await
+            .ok()
+            .and_then(|response| latest_time(&response.data));
+
+        let response = self.nlp(device_id, text).await?;
+
+        for _ in 0..ASK_POLL_ATTEMPTS {
+            sleep(ASK_POLL_INTERVAL).await;
+            let result = self
+                .ubus_call(device_id, "mibrain", "nlp_result_get", "{}")
+                .await?;
+            let latest = nlp_records(&result.data)?
+                .into_iter()
+                .max_by_key(nlp_timestamp)
+                .filter(|nlp| before.is_none_or(|before| nlp_timestamp(nlp) > before));
+            if let Some(nlp) = latest {
+                trace!("询问的结果: {}", nlp);
+                return Ok(NlpResult::from_nlp(nlp));
+            }
+        }
+        warn!("等待小爱回答超时，返回原始结果");
+
+        Ok(NlpResult {
+            raw: response.data,
+            ..Default::default()
+        })
+    }
+
     /// 获取小爱音箱的对话记录（推荐方法 - 使用 conversation API）。
     ///
     /// 该方法使用与 xiaomusic 相同的 API，能够更可靠地获取最近的对话记录。
@@ -399,22 +1553,9 @@ impl Xiaoai {
             hardware, timestamp, limit
         );
 
-        // 从 cookie_store 中提取必要的 cookie 信息
-        let cookie_store = self.cookie_store.lock().unwrap();
-        let api_url = Url::parse(self.server.as_str())?;
-        
-        let mut service_token = String::new();
-        let mut user_id = String::new();
-        
-        // 从 API 服务器的 cookie 中提取信息
-        for cookie in cookie_store.matches(&api_url) {
-            match cookie.name() {
-                "serviceToken" => service_token = cookie.value().to_string(),
-                "userId" => user_id = cookie.value().to_string(),
-                _ => {}
-            }
-        }
-        drop(cookie_store);
+        // 从 API 服务器的 cookie 中提取必要的信息
+        let service_token = self.cookie_value("serviceToken").unwrap_or_default();
+        let user_id = self.cookie_value("userId").unwrap_or_default();
         
         trace!("使用 deviceId={}, userId={}, serviceToken 长度={}", device_id, user_id, service_token.len());
         
@@ -433,6 +1574,11 @@ impl Xiaoai {
 
         let status = http_resp.status();
         trace!("Conversation API HTTP状态: {}", status);
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(crate::Error::RateLimited {
+                retry_after: retry_after(&http_resp),
+            });
+        }
         
         if !status.is_success() {
             let body = http_resp.text().await?;
@@ -446,15 +1592,10 @@ impl Xiaoai {
 
         let resp = http_resp.json::<ConversationResponse>().await?;
 
-        if resp.code != 0 {
-            // 构造一个 XiaoaiResponse 用于返回错误
-            let error_resp = XiaoaiResponse {
-                code: resp.code as i64,
-                message: format!("Conversation API 返回错误码: {}", resp.code),
-                data: resp.data.clone(),
-            };
-            return Err(crate::Error::Api(error_resp));
+        if resp.code == RATE_LIMITED_CODE {
+            return Err(crate::Error::RateLimited { retry_after: None });
         }
+        let resp = resp.error_for_code()?;
 
         // 解析 data 字段（可能是字符串形式的 JSON）
         let data_str = if let Some(data) = resp.data.as_str() {
@@ -477,8 +1618,18 @@ impl Xiaoai {
     }
 }
 
+/// [`Xiaoai::tts_with`] 的播报参数，未设置的项使用设备的默认值。
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TtsOptions {
+    /// 语速，`1.0` 为正常语速
+    pub speed: Option<f32>,
+    /// 音色
+    pub speaker: Option<String>,
+}
+
 /// 表示播放器的播放状态。
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum PlayState {
     Play,
     Pause,
@@ -487,8 +1638,34 @@ pub enum PlayState {
     Toggle,
 }
 
+/// 播放器的循环模式。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoopMode {
+    /// 顺序播放，播完列表后停止。
+    Sequence,
+    /// 单曲循环。
+    SingleLoop,
+    /// 列表循环。
+    ListLoop,
+    /// 随机播放。
+    Shuffle,
+}
+
+/// 循环模式在 `player_set_loop` 中对应的 `type`。
+///
+/// 取值来自社区经验，目前没有发现机型间的差异，发现后在这里按机型区分。
+fn loop_code(mode: LoopMode) -> u32 {
+    match mode {
+        LoopMode::SingleLoop => 0,
+        LoopMode::ListLoop => 1,
+        LoopMode::Sequence => 2,
+        LoopMode::Shuffle => 3,
+    }
+}
+
 /// 小爱设备信息。
-#[derive(Clone, Deserialize, Debug)]
+#[derive(Clone, Deserialize, Serialize, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct DeviceInfo {
     /// 设备 ID。
@@ -502,24 +1679,405 @@ pub struct DeviceInfo {
 
     /// 机型。
     pub hardware: String,
+
+    /// 在线状态，如 `online`、`offline`。
+    #[serde(default)]
+    pub presence: Option<String>,
+
+    /// 固件版本。
+    #[serde(default)]
+    pub rom_version: Option<String>,
+
+    /// 设备所在的分组，多房间、立体声组合等场景下才有。
+    ///
+    /// 接口没有公开分组字段的格式，这里兼容 `group` 和 `groupName`，缺失时为 `None`。
+    /// 通常需要用 [`Xiaoai::device_info_with`] 以 `master = true` 请求才会返回。
+    #[serde(default, alias = "groupName", skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+
+    /// 设备的别名。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+
+    /// 设备的 MAC 地址。
+    #[serde(
+        default,
+        rename = "mac",
+        alias = "macAddress",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mac_address: Option<String>,
+
+    /// 设备支持的能力，如 `{"school_timetable": 1, "night_mode": 1}`。
+    ///
+    /// 不同机型和固件的内容差别很大，这里保留原始的 JSON。
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<Value>,
+}
+
+impl DeviceInfo {
+    /// 设备是否在线，取自 [`DeviceInfo::presence`]，缺失时为 `None`。
+    pub fn is_online(&self) -> Option<bool> {
+        self.presence
+            .as_deref()
+            .map(|presence| presence.eq_ignore_ascii_case("online"))
+    }
+
+    /// 设备是否支持某项能力，取自 [`DeviceInfo::capabilities`]。
+    ///
+    /// 能力的值为非零数字或 `true` 时视为支持，缺失时视为不支持。
+    ///
+    /// ```
+    /// let info: miai::DeviceInfo = serde_json::from_value(serde_json::json!({
+    ///     "deviceID": "id",
+    ///     "name": "小爱音箱",
+    ///     "hardware": "LX06",
+    ///     "mac": "00:11:22:33:44:55",
+    ///     "capabilities": {"night_mode": 1, "child_mode": 0},
+    /// }))
+    /// .unwrap();
+    /// assert_eq!(info.mac_address.as_deref(), Some("00:11:22:33:44:55"));
+    /// assert!(info.has_capability("night_mode"));
+    /// assert!(!info.has_capability("child_mode"));
+    /// assert!(!info.has_capability("school_timetable"));
+    /// ```
+    pub fn has_capability(&self, name: &str) -> bool {
+        match self.capabilities.as_ref().and_then(|capabilities| capabilities.get(name)) {
+            Some(Value::Bool(enabled)) => *enabled,
+            Some(Value::Number(number)) => number.as_f64().is_some_and(|number| number != 0.0),
+            _ => false,
+        }
+    }
+}
+
+/// 当前登录账号的信息，由 [`Xiaoai::account_info`] 返回。
+#[derive(Clone, Debug)]
+pub struct AccountInfo {
+    /// 用户 ID，即小米账号的 ID
+    pub user_id: String,
+    /// 账号下的设备
+    pub devices: Vec<DeviceInfo>,
+    /// 登录状态的过期时间，参见 [`Xiaoai::token_expiry`]
+    pub token_expiry: Option<OffsetDateTime>,
+}
+
+/// 设备的在线状态与常用属性，由 [`Xiaoai::device_status`] 返回。
+///
+/// 设备离线或读取播放器状态失败时，播放相关的字段为 `None`。
+#[derive(Clone, Debug)]
+pub struct DeviceStatus {
+    /// 设备信息
+    pub info: DeviceInfo,
+    /// 是否在线，设备列表中没有在线状态时为 `None`
+    pub online: Option<bool>,
+    /// 固件版本
+    pub firmware: Option<String>,
+    /// 音量
+    pub volume: Option<u32>,
+    /// 播放状态
+    pub play_state: Option<PlayState>,
+}
+
+/// 解析 `nlp_result_get` 的返回中的每一条 NLP 结果。
+///
+/// `info` 是 JSON 字符串，其中 `result` 数组每一项的 `nlp` 又是一层 JSON 字符串。
+fn nlp_records(data: &Value) -> crate::Result<Vec<Value>> {
+    let unavailable = |fragment: &str| crate::Error::ConversationUnavailable(fragment.to_string());
+
+    let info = data["info"]
+        .as_str()
+        .ok_or_else(|| unavailable(&data.to_string()))?;
+    let info: Value = serde_json::from_str(info).map_err(|_| unavailable(info))?;
+    let Some(results) = info["result"].as_array() else {
+        return Ok(Vec::new());
+    };
+
+    results
+        .iter()
+        .filter_map(|item| item["nlp"].as_str())
+        .map(|nlp| serde_json::from_str(nlp).map_err(|_| unavailable(nlp)))
+        .collect()
+}
+
+fn nlp_timestamp(nlp: &Value) -> i64 {
+    nlp["meta"]["timestamp"].as_i64().unwrap_or_default()
+}
+
+/// [`Xiaoai::ask`] 解析出的回答。
+///
+/// # Examples
+///
+/// ```
+/// use miai::NlpResult;
+///
+/// let nlp = serde_json::json!({
+///     "meta": {"timestamp": 1729766145000_i64},
+///     "response": {"answer": [{
+///         "domain": "weather",
+///         "intention": {"query": "今天天气怎么样", "domain": "weather", "intent": "query"},
+///         "content": {"to_speak": "今天晴，25度"}
+///     }]}
+/// });
+/// let result = NlpResult::from_nlp(nlp);
+/// assert_eq!(result.answer.as_deref(), Some("今天晴，25度"));
+/// assert_eq!(result.domain.as_deref(), Some("weather"));
+/// assert!(!result.playing);
+/// ```
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct NlpResult {
+    /// 小爱识别出的问题
+    pub query: Option<String>,
+    /// 小爱回答的文本，多个回答会按顺序拼接
+    pub answer: Option<String>,
+    /// 意图域，如 `weather`、`music`
+    pub domain: Option<String>,
+    /// 意图
+    pub intent: Option<String>,
+    /// 是否触发了播放
+    pub playing: bool,
+    /// 完整的 NLP 结果
+    pub raw: Value,
+}
+
+impl NlpResult {
+    /// 从 `nlp_result_get` 中的一条 NLP 结果解析，无法识别的字段为 `None`。
+    pub fn from_nlp(nlp: Value) -> Self {
+        let answers = nlp["response"]["answer"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let find = |f: fn(&Value) -> Option<&str>| {
+            answers
+                .iter()
+                .find_map(|answer| f(answer).filter(|s| !s.is_empty()))
+                .map(str::to_string)
+        };
+
+        let query = find(|answer| answer["intention"]["query"].as_str());
+        let domain = find(|answer| {
+            answer["domain"]
+                .as_str()
+                .or_else(|| answer["intention"]["domain"].as_str())
+        });
+        let intent = find(|answer| answer["intention"]["intent"].as_str());
+        let answer: String = answers
+            .iter()
+            .filter_map(|answer| answer["content"]["to_speak"].as_str())
+            .collect();
+        let playing = answers.iter().any(|answer| {
+            answer["type"]
+                .as_str()
+                .is_some_and(|kind| kind.eq_ignore_ascii_case("audio"))
+        }) || domain
+            .as_deref()
+            .is_some_and(|domain| PLAYBACK_DOMAINS.contains(&domain));
+
+        Self {
+            query,
+            answer: (!answer.is_empty()).then_some(answer),
+            domain,
+            intent,
+            playing,
+            raw: nlp,
+        }
+    }
+}
+
+/// 从 `nlp_result_get` 的返回中解析出时间最晚的对话。
+fn parse_nlp_result(data: &Value) -> crate::Result<Option<Conversation>> {
+    let mut latest: Option<Conversation> = None;
+    for nlp in nlp_records(data)? {
+        let answers = nlp["response"]["answer"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let query = answers
+            .iter()
+            .find_map(|answer| answer["intention"]["query"].as_str())
+            .unwrap_or_default();
+        let answers = answers
+            .iter()
+            .filter_map(|answer| answer["content"]["to_speak"].as_str())
+            .filter(|text| !text.is_empty())
+            .map(|text| ConversationAnswer {
+                tts: Some(TtsInfo {
+                    text: text.to_string(),
+                }),
+            })
+            .collect();
+        let conversation = Conversation {
+            time: nlp_timestamp(&nlp),
+            query: query.to_string(),
+            answers,
+        };
+
+        if latest.as_ref().is_none_or(|latest| conversation.time >= latest.time) {
+            latest = Some(conversation);
+        }
+    }
+
+    Ok(latest)
+}
+
+/// 设备的机型信息，未收录的机型使用 [`DEFAULT_MODEL`]。
+fn device_model(device: &DeviceInfo) -> &'static ModelInfo {
+    model_info(&device.hardware).unwrap_or(&DEFAULT_MODEL)
+}
+
+/// 依次完成 [`Login`] 的三个步骤。
+async fn complete_login(login: &Login) -> crate::Result<()> {
+    let login_response = login.login().await?;
+    let auth_response = login.auth(login_response).await?;
+    login.get_token(auth_response).await?;
+
+    Ok(())
+}
+
+/// 设备列表接口的路径，`master` 以 `0`、`1` 传递。
+fn device_list_uri(master: bool) -> String {
+    format!("admin/v2/device_list?master={}", u8::from(master))
+}
+
+/// 超时、连接失败和服务端错误通常是暂时的，可以重试。
+fn is_transient(e: &reqwest::Error) -> bool {
+    e.is_timeout() || e.is_connect() || e.status().is_some_and(|s| s.is_server_error())
 }
 
-fn random_request_id() -> String {
-    let mut request_id = random_id(30);
-    request_id.insert_str(0, "app_ios_");
+/// 读取 `Retry-After` 响应头，只支持秒数的格式。
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response.headers().get(reqwest::header::RETRY_AFTER)?;
+    let secs = value.to_str().ok()?.trim().parse().ok()?;
 
-    request_id
+    Some(Duration::from_secs(secs))
 }
 
 /// 播放器状态的宽松表示。保留原始返回的 JSON 在 `raw` 字段中，
-/// 并提供一些方便读取的可选字段。
+/// 并提供一些解析好的可选字段。
+///
+/// 不同机型、固件返回的结构不完全相同，字段在构造时从 `raw` 中宽松地查找，找不到时为 `None`。
+///
+/// # Examples
+///
+/// ```
+/// use miai::{PlayState, PlayerStatus};
+///
+/// let status = PlayerStatus::from_raw(serde_json::json!({
+///     "info": {"status": "playing", "vol": "35", "loop_type": 1}
+/// }));
+/// assert_eq!(status.status, Some(PlayState::Play));
+/// assert_eq!(status.volume, Some(35));
+/// assert_eq!(status.loop_type, Some(1));
+///
+/// let status = PlayerStatus::from_raw(serde_json::json!({
+///     "info": {"status": 1, "play_song_detail": {"title": "晴天", "artist": "周杰伦"}}
+/// }));
+/// assert_eq!(status.title.as_deref(), Some("晴天"));
+/// assert_eq!(status.artist.as_deref(), Some("周杰伦"));
+/// ```
 #[derive(Clone, Debug, Deserialize)]
+#[serde(from = "Value")]
 pub struct PlayerStatus {
+    /// 播放状态。
+    pub status: Option<PlayState>,
+
+    /// 音量。
+    pub volume: Option<u32>,
+
+    /// 循环模式的原始取值。
+    pub loop_type: Option<u32>,
+
+    /// 当前曲目的标题。
+    pub title: Option<String>,
+
+    /// 当前曲目的艺术家。
+    pub artist: Option<String>,
+
     /// 原始返回的 data 字段（通常是 JSON 对象）
-    #[serde(flatten)]
     pub raw: Value,
 }
 
+impl PlayerStatus {
+    /// 从原始 JSON 构造，并解析其中的常见字段。
+    pub fn from_raw(raw: Value) -> Self {
+        Self {
+            status: find_field(&raw, &["status"]).and_then(parse_status),
+            volume: find_field(&raw, &["volume", "vol"]).and_then(value_as_u32),
+            loop_type: find_field(&raw, &["loop_type"]).and_then(value_as_u32),
+            title: find_track_field(&raw, &["title", "name", "song_name"]),
+            artist: find_track_field(&raw, &["artist", "singer", "artist_name"]),
+            raw,
+        }
+    }
+
+    /// 播放状态，同 [`PlayerStatus::status`] 字段。
+    pub fn play_state(&self) -> Option<PlayState> {
+        self.status.clone()
+    }
+
+    /// 音量，同 [`PlayerStatus::volume`][PlayerStatus#structfield.volume] 字段。
+    pub fn volume(&self) -> Option<u32> {
+        self.volume
+    }
+}
+
+impl From<Value> for PlayerStatus {
+    fn from(raw: Value) -> Self {
+        Self::from_raw(raw)
+    }
+}
+
+/// 依次在 `info` 和顶层中查找 `keys` 中的字段，`info` 优先。
+fn find_field<'a>(raw: &'a Value, keys: &[&str]) -> Option<&'a Value> {
+    [raw.get("info"), Some(raw)]
+        .into_iter()
+        .flatten()
+        .flat_map(|value| keys.iter().map(move |key| value.get(key)))
+        .flatten()
+        .find(|value| !value.is_null())
+}
+
+/// 查找曲目信息中的非空字符串，依次在 `info.play_song_detail` 和 `info` 中查找。
+fn find_track_field(raw: &Value, keys: &[&str]) -> Option<String> {
+    let info = raw.get("info");
+    [info.and_then(|info| info.get("play_song_detail")), info]
+        .into_iter()
+        .flatten()
+        .flat_map(|value| keys.iter().filter_map(move |key| value.get(key)?.as_str()))
+        .map(str::trim)
+        .find(|text| !text.is_empty())
+        .map(str::to_string)
+}
+
+/// 解析播放状态。
+///
+/// 状态码 `0` 表示停止，`1` 表示播放，`2` 表示暂停，部分固件使用字符串表示，如 `"playing"`。
+fn parse_status(value: &Value) -> Option<PlayState> {
+    if let Some(code) = value_as_u32(value) {
+        return match code {
+            0 => Some(PlayState::Stop),
+            1 => Some(PlayState::Play),
+            2 => Some(PlayState::Pause),
+            _ => None,
+        };
+    }
+
+    match value.as_str()?.trim().to_ascii_lowercase().as_str() {
+        "play" | "playing" => Some(PlayState::Play),
+        "pause" | "paused" => Some(PlayState::Pause),
+        "stop" | "stopped" | "idle" => Some(PlayState::Stop),
+        _ => None,
+    }
+}
+
+/// 把数字或数字字符串解析为 `u32`。
+fn value_as_u32(value: &Value) -> Option<u32> {
+    match value {
+        Value::Number(n) => u32::try_from(n.as_u64()?).ok(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
 /// 小爱音箱的消息记录。
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -554,20 +2112,54 @@ pub struct AnswerRecord {
 /// Conversation API 的响应结构
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConversationResponse {
-    pub code: i32,
+    #[serde(deserialize_with = "crate::util::deserialize_code")]
+    pub code: i64,
     pub data: Value,
 }
 
+impl ConversationResponse {
+    /// 同 [`XiaoaiResponse::error_for_code`]，`code` 不为 0 时返回 [`Error::Api`][crate::Error::Api]。
+    pub fn error_for_code(self) -> crate::Result<Self> {
+        if self.code == 0 {
+            return Ok(self);
+        }
+
+        Err(crate::Error::Api(XiaoaiResponse {
+            code: self.code,
+            message: format!("Conversation API 返回错误码: {}", self.code),
+            data: self.data,
+        }))
+    }
+}
+
 /// 对话数据的包装结构
 #[derive(Clone, Debug, Deserialize)]
 pub struct ConversationData {
     pub records: Vec<Conversation>,
 }
 
-/// 单条对话记录
-#[derive(Clone, Debug, Deserialize)]
+/// 单条对话记录。
+///
+/// 由 [`Xiaoai::get_conversations`] 返回，[`ConversationWatcher`][crate::ConversationWatcher]
+/// 的 [`KeywordMatch`][crate::KeywordMatch] 中也使用同一类型。
+///
+/// # Examples
+///
+/// ```
+/// use miai::Conversation;
+///
+/// let payload = r#"{
+///     "time": 1729766145000,
+///     "query": "吃饭了吗",
+///     "answers": [{"type": "TTS", "tts": {"text": "别提了"}}]
+/// }"#;
+/// let conversation: Conversation = serde_json::from_str(payload).unwrap();
+/// assert_eq!(conversation.query, "吃饭了吗");
+/// assert_eq!(conversation.answer().as_deref(), Some("别提了"));
+/// ```
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Conversation {
-    /// 时间戳（秒）
+    /// 时间戳（毫秒）
     pub time: i64,
     
     /// 用户的查询/问题
@@ -579,8 +2171,22 @@ pub struct Conversation {
     pub answers: Vec<ConversationAnswer>,
 }
 
+impl Conversation {
+    /// 小爱回答的文本，多个回答会按顺序拼接；没有文本回答时返回 `None`。
+    pub fn answer(&self) -> Option<String> {
+        let text: String = self
+            .answers
+            .iter()
+            .filter_map(|answer| answer.tts.as_ref())
+            .map(|tts| tts.text.as_str())
+            .collect();
+
+        (!text.is_empty()).then_some(text)
+    }
+}
+
 /// 对话中的单个回答
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ConversationAnswer {
     /// TTS 信息（语音合成的文本）
     #[serde(default)]
@@ -588,9 +2194,36 @@ pub struct ConversationAnswer {
 }
 
 /// TTS 文本信息
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct TtsInfo {
     /// 要播报的文本内容
     #[serde(default)]
     pub text: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn device_list_master_param() {
+        assert_eq!(device_list_uri(false), "admin/v2/device_list?master=0");
+        assert_eq!(device_list_uri(true), "admin/v2/device_list?master=1");
+    }
+
+    #[test]
+    fn conversation_response_error_for_code() {
+        let ok: ConversationResponse =
+            serde_json::from_value(json!({"code": 0, "data": "{}"})).unwrap();
+        assert!(ok.error_for_code().is_ok());
+
+        let failed: ConversationResponse =
+            serde_json::from_value(json!({"code": "101", "data": null})).unwrap();
+        match failed.error_for_code() {
+            Err(crate::Error::Api(response)) => assert_eq!(response.code, 101),
+            other => panic!("应返回 Error::Api，实际为 {:?}", other),
+        }
+    }
+}